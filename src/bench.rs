@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::search::*;
+use crate::ttable::*;
+use crate::{KIWIPETE, POS3, POS3_CASTLE, POS4, POS4_CASTLE, POS5, POS5_CASTLE, POS6};
+use crate::{SEARCH_STACK_SIZE, TEST_FEN, TEST_FEN2};
+
+/* fixed depth search of a fixed set of positions. the total node count is a signature of
+the search: a change that leaves it the same did not change what the engine does, for
+any change that should not, compare it before and after. "bench pruning" gives the counts
+with each of the pruning options off, to see what each of them saves.
+
+note: the signature only holds for the default search options, single threaded, in builds
+      of the same version. */
+
+pub const BENCH_DEPTH: usize = 8;
+
+#[rustfmt::skip]
+const BENCH_FENS: [&str; 42] = [
+    INITIAL_CHESS_POS_FEN,
+    "r3k2r/2pb1ppp/2pp1q2/p7/1nP1B3/1P2P3/P2N1PPP/R2QK2R w KQkq a6 0 14",
+    "4rrk1/2p1b1p1/p1p3q1/4p3/2P2n1p/1P1NR2P/PB3PP1/3R1QK1 b - - 2 24",
+    "r3qbrk/6p1/2b2pPp/p3pP1Q/PpPpP2P/3P1B2/2PB3K/R5R1 w - - 16 42",
+    "6k1/1R3p2/6p1/2Bp3p/3P2q1/P7/1P2rQ1K/5R2 b - - 4 44",
+    "8/8/1p2k1p1/3p3p/1p1P1P1P/1P2PK2/8/8 w - - 3 54",
+    "7r/2p3k1/1p1p1qp1/1P1Bp3/p1P2r1P/P7/4R3/Q4RK1 w - - 0 36",
+    "r1bq1rk1/pp2b1pp/n1pp1n2/3P1p2/2P1p3/2N1P2N/PP2BPPP/R1BQ1RK1 b - - 2 10",
+    "3r3k/2r4p/1p1b3q/p4P2/P2Pp3/1B2P3/3BQ1RP/6K1 w - - 3 87",
+    "2r4r/1p4k1/1Pnp4/3Qb1pq/8/4BpPp/5P2/2RR1BK1 w - - 0 42",
+    "4q1bk/6b1/7p/p1p4p/PNPpP2P/KN4P1/3Q4/4R3 b - - 0 37",
+    "2q3r1/1r2pk2/pp3pp1/2pP3p/P1Pb1BbP/1P4Q1/R3NPP1/4R1K1 w - - 2 34",
+    "1r2r2k/1b4q1/pp5p/2pPp1p1/P3Pn2/1P1B1Q1P/2R3P1/4BR1K b - - 1 37",
+    "r3kbbr/pp1n1p1P/3ppnp1/q5N1/1P1pP3/P1N1B3/2P1QP2/R3KB1R b KQkq b3 0 17",
+    "8/6pk/2b1Rp2/3r4/1R1B2PP/P5K1/8/2r5 b - - 16 42",
+    "1r4k1/4ppb1/2n1b1qp/pB4p1/1n1BP1P1/7P/2PNQPK1/3RN3 w - - 8 29",
+    "8/p2B4/PkP5/4p1pK/4Pb1p/5P2/8/8 w - - 29 68",
+    "3r4/ppq1ppkp/4bnp1/2pN4/2P1P3/1P4P1/PQ3PBP/R4K2 b - - 2 20",
+    "5rr1/4n2k/4q2P/P1P2n2/3B1p2/4pP2/2N1P3/1RR1K2Q w - - 1 49",
+    "1r5k/2pq2p1/3p3p/p1pP4/4QP2/PP1R3P/6PK/8 w - - 1 51",
+    "q5k1/5ppp/1r3bn1/1B6/P1N2P2/BQ2P1P1/5K1P/8 b - - 2 34",
+    "r1b2k1r/5n2/p4q2/1ppn1Pp1/3pp1p1/NP2P3/P1PPBK2/1RQN2R1 w - - 0 22",
+    "r1bqk2r/pppp1ppp/5n2/4b3/4P3/P1N5/1PP2PPP/R1BQKB1R w KQkq - 0 5",
+    "r1bqr1k1/pp1p1ppp/2p5/8/3N1Q2/P2BB3/1PP2PPP/R3K2n b Q - 1 12",
+    "r1bq2k1/p4r1p/1pp2pp1/3p4/1P1B3Q/P2B1N2/2P3PP/4R1K1 b - - 2 19",
+    "r4qk1/6r1/1p4p1/2ppBbN1/1p5Q/P7/2P3PP/5RK1 w - - 2 25",
+    "r7/6k1/1p6/2pp1p2/7Q/8/p1P2K1P/8 w - - 0 32",
+    "r3k2r/ppp1pp1p/2nqb1pn/3p4/4P3/2PP4/PP1NBPPP/R2QK1NR w KQkq - 1 5",
+    "3r1rk1/1pp1pn1p/p1n1q1p1/3p4/Q3P3/2P5/PP1NBPPP/4RRK1 w - - 0 12",
+    "5rk1/1pp1pn1p/p3Brp1/8/1n6/5N2/PP3PPP/2R2RK1 w - - 2 20",
+    "8/1p2pk1p/p1p1r1p1/3n4/8/5R2/PP3PPP/4R1K1 b - - 3 27",
+    "8/4pk2/1p1r2p1/p1p4p/Pn5P/3R4/1P3PP1/4RK2 w - - 1 33",
+    "8/5k2/1pnrp1p1/p1p4p/P6P/4R1PK/1P3P2/4R3 b - - 1 38",
+    "8/8/1p1kp1p1/p1pr1n1p/P6P/1R4P1/1P3PK1/1R6 b - - 15 45",
+    "8/8/1p1k2p1/p1prp2p/P2n3P/6P1/1P1R1PK1/4R3 b - - 5 49",
+    "8/8/1p4p1/p1p2k1p/P2npP1P/4K1P1/1P6/3R4 w - - 6 54",
+    "8/8/1p4p1/p1p2k1p/P2n1P1P/4K1P1/1P6/6R1 b - - 6 59",
+    "8/5k2/1p4p1/p1pK3p/P2n1P1P/6P1/1P6/4R3 b - - 14 63",
+    "8/1R6/1p1K1kp1/p6p/P1p2P1P/6P1/1Pn5/8 w - - 0 67",
+    "1rb1rn1k/p3q1bp/2p3p1/2p1p3/2P1P2N/PP1RQNP1/1B3P2/4R1K1 b - - 4 23",
+    "6k1/5p2/6p1/p5Pp/4P2P/8/6K1/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 80",
+];
+
+// the positions of BENCH_FENS, the perft test positions and the test fens
+pub fn bench_positions() -> Vec<ChessBoard> {
+    let mut positions: Vec<ChessBoard> =
+        BENCH_FENS.iter().map(|x| ChessBoard::from_fen(x)).collect();
+    let bitboards = [
+        (KIWIPETE, [true; 4], Side::White),
+        (POS3, POS3_CASTLE, Side::White),
+        (POS4, POS4_CASTLE, Side::White),
+        (POS5, POS5_CASTLE, Side::White),
+        // note: the black king is in check, so only legal with black to move
+        (POS6, [false; 4], Side::Black),
+    ];
+    for (piece_bbs, castle_bools, side_to_move) in bitboards {
+        let chessboard = ChessBoard {
+            piece_bbs,
+            mailbox: generate_mailbox(piece_bbs),
+            castle_bools,
+            side_to_move,
+            ..ChessBoard::default()
+        };
+        // through a fen, so that the check bitboard and the hash are set up
+        positions.push(ChessBoard::from_fen(chessboard.to_fen().as_str()));
+    }
+    positions.push(ChessBoard::from_fen(TEST_FEN));
+    positions.push(ChessBoard::from_fen(TEST_FEN2));
+    positions.push(ChessBoard::from_fen(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ));
+    positions
+}
+
+// searches every bench position to depth with an empty hash, and prints the total
+// note: runs on its own thread for the larger stack, and returns the total node count
+pub fn bench(depth: usize) -> u64 {
+    bench_with_config(depth, SearchConfig::default())
+}
+
+// bench with the search options of config
+pub fn bench_with_config(depth: usize, config: SearchConfig) -> u64 {
+    let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn(move || {
+        let positions = bench_positions();
+        let limits = SearchLimits { depth: Some(depth), ..SearchLimits::default() };
+        let tt = TranspositionTable::new(DEFAULT_HASH_MB);
+        // note: the pawn table only caches, keeping it does not change the node count
+        let mut evaluators = vec![HandCraftedEval::default()];
+        let start = Instant::now();
+        let mut nodes: u64 = 0;
+        for (i, chessboard) in positions.iter().enumerate() {
+            println!("info string position {}/{}: {}", i + 1, positions.len(), chessboard.to_fen());
+            tt.clear();
+            let signals = Arc::new(SearchSignals::default());
+            nodes += chessboard
+                .search_with_evaluators(&limits, &config, &tt, &mut evaluators, signals)
+                .nodes;
+        }
+        let elapsed = start.elapsed().as_millis() as u64;
+        println!("===========================");
+        println!("Total time (ms) : {}", elapsed);
+        println!("Nodes searched  : {}", nodes);
+        println!("Nodes/second    : {}", nodes * 1000 / elapsed.max(1));
+        nodes
+    });
+    match handle.map(|x| x.join()) {
+        Ok(Ok(nodes)) => nodes,
+        _ => panic!("bench error: bench thread failed!"),
+    }
+}
+
+// runs the bench with late move reductions, late move pruning and futility pruning all on,
+// then with each of them off in turn, and prints the node counts side by side
+pub fn pruning_bench(depth: usize) -> Vec<(SearchConfig, u64)> {
+    let default = SearchConfig::default();
+    let configs = [
+        default,
+        SearchConfig { lmr: false, ..default },
+        SearchConfig { lmp: false, ..default },
+        SearchConfig { futility: false, ..default },
+        SearchConfig { lmr: false, lmp: false, futility: false, ..default },
+    ];
+    let mut results: Vec<(SearchConfig, u64)> = Vec::new();
+    for config in configs {
+        results.push((config, bench_with_config(depth, config)));
+    }
+
+    let on_off = |x: bool| match x {
+        true => "on",
+        false => "off",
+    };
+    println!("===========================");
+    println!("depth {}", depth);
+    println!("lmr  lmp  futility  nodes");
+    for (config, nodes) in results.iter() {
+        println!(
+            "{:<4} {:<4} {:<9} {}",
+            on_off(config.lmr),
+            on_off(config.lmp),
+            on_off(config.futility),
+            nodes
+        );
+    }
+    results
+}
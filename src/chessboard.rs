@@ -7,6 +7,8 @@ use std::fmt::Display;
 use crate::bitboard::*;
 use crate::chessboard;
 use crate::chessmove::*;
+use crate::eval::*;
+use crate::search::*;
 
 // note: castle_bools[] = [white-king  side castle,
 //                         white-queen side castle,
@@ -27,8 +29,11 @@ pub struct ChessBoard {
     pub half_move_clock: usize,
     pub full_move_counter: usize,
     pub current_hash: u64,
-    pub repeats: [u8; 1 << 14],
-    pub pv: MovesArray,
+    pub pst_score: TaperedScore, //piece-square total, updated like current_hash
+    pub phase: i32,
+    pub pawn_hash: u64, //zobrist hash of the pawns only, for the pawn hash table
+    pub hash_history: [u64; HISTORY_SIZE],
+    pub history_len: usize,
 }
 
 pub type CB = ChessBoard;
@@ -45,8 +50,11 @@ impl Default for ChessBoard {
             half_move_clock: 0,
             full_move_counter: 0,
             current_hash: 1544757369275567478, //assuming the constants aren't changed
-            repeats: [0; 1 << 14],
-            pv: MovesArray::new(),
+            pst_score: TaperedScore::ZERO,     //the initial position is symmetric
+            phase: MAX_PHASE,
+            pawn_hash: ZH::pawn_hash(&INITIAL_CHESS_POS),
+            hash_history: [0; HISTORY_SIZE],
+            history_len: 0,
         }
     }
 }
@@ -85,6 +93,11 @@ impl Display for ChessBoard {
     }
 }
 
+// hashes of earlier positions are kept in a ring buffer, positions further back than this
+// are unreachable anyway as the fifty-move rule would have ended the game
+pub const HISTORY_SIZE: usize = 128;
+pub const LIGHT_SQUARES: BB = BB { data: 0xAA55AA55AA55AA55 };
+
 const ASCII_SYM: [char; 12] = ['K', 'Q', 'N', 'B', 'R', 'P', 'k', 'q', 'n', 'b', 'r', 'p'];
 const UNICODE_SYM: [char; 12] = ['♚', '♛', '♞', '♝', '♜', '♟', '♔', '♕', '♘', '♗', '♖', '♙'];
 const W_KING_SIDE_CASTLE_MASK: BB = BB { data: 0b00000110 };
@@ -264,57 +277,6 @@ pub const fn square_index(square_name: &str) -> usize {
 type OCM = Option<ChessMove>;
 
 impl ChessBoard {
-    pub const fn search(&self, depth: usize) -> ChessMove {
-        match self.negamax(isize::MIN + 1, isize::MAX - 1, depth).1 {
-            Some(x) => x,
-            None => panic!("search error: no legal move!"),
-        }
-    }
-
-    const fn negamax(&self, alpha: isize, beta: isize, depth: usize) -> (isize, OCM) {
-        if depth == 0 {
-            return match self.side_to_move {
-                Side::White => (self.naive_eval(), None),
-                Side::Black => (-self.naive_eval(), None),
-            };
-        }
-
-        let moves_array = self.generate_moves();
-        //sort moves_array here
-
-        if moves_array.len() == 0 && self.king_is_in_check(self.side_to_move) {
-            return (((isize::MIN + 1) / 2) - (depth as isize), None);
-        }
-        let mut alpha = alpha;
-        let mut value: isize = isize::MIN + 1;
-        let mut i: usize = 0;
-        let mut best_move: Option<ChessMove> = None;
-        while i < moves_array.len() {
-            let chess_move = match moves_array.data[i] {
-                Some(x) => x,
-                None => unreachable!(),
-            };
-            let chessboard = self.update_state(chess_move);
-            let new_value = -chessboard.negamax(-beta, -alpha, depth - 1).0;
-            // value = max(value, new_value)
-            if new_value > value {
-                value = new_value;
-            }
-            // alpha = max(alpha, value)
-            if value > alpha {
-                alpha = value;
-                best_move = Some(chess_move);
-            }
-
-            // cutoff
-            if alpha >= beta {
-                break;
-            }
-            i += 1;
-        }
-        (value, best_move)
-    }
-
     const fn old_negamax_neg(&self, alpha: isize, beta: isize, depth: usize) -> (isize, OCM) {
         let (x, y) = self.old_negamax(alpha, beta, depth);
         return (-x, y);
@@ -409,62 +371,43 @@ impl ChessBoard {
         let mut chessboard = ChessBoard {
             piece_bbs: [BB::ZERO; 12],
             mailbox: [None; 64],
-            castle_bools: [true; 4],
+            castle_bools: [false; 4],
             enpassant_bb: BB::ZERO,
             check_bb: BB::ZERO,
             side_to_move: Side::White,
             half_move_clock: 0,
             full_move_counter: 0,
             current_hash: 0,
-            repeats: [0; 1 << 14],
-            pv: MovesArray::new(),
+            pst_score: TaperedScore::ZERO,
+            phase: 0,
+            pawn_hash: 0,
+            hash_history: [0; HISTORY_SIZE],
+            history_len: 0,
         };
         assert!(input.is_ascii());
         let input_vec: Vec<&str> = input.split_ascii_whitespace().collect();
         assert!(input_vec.len() == 6);
 
-        // parse piece placement data
-        let mut i: usize = 0;
-        while i < 8 {
-            let mut j = 0usize;
-            while j < 8 {
-                let square: usize = 8 * i + j;
-                let mut k: usize = 0;
-                while k < input_vec[0].len() {
-                    let s = match input_vec[0].chars().nth(k) {
-                        Some(x) => x,
-                        None => unreachable!(),
-                    };
-                    if s.is_ascii_alphabetic() {
-                        chessboard.piece_bbs[sym_index(s)].data |= 1u64 << square;
-                        let piece_data = match s {
-                            'K' => (Side::White, PieceType::King),
-                            'Q' => (Side::White, PieceType::Queen),
-                            'N' => (Side::White, PieceType::Knight),
-                            'B' => (Side::White, PieceType::Bishop),
-                            'R' => (Side::White, PieceType::Rook),
-                            'P' => (Side::White, PieceType::Pawn),
-                            'k' => (Side::Black, PieceType::King),
-                            'q' => (Side::Black, PieceType::Queen),
-                            'n' => (Side::Black, PieceType::Knight),
-                            'b' => (Side::Black, PieceType::Bishop),
-                            'r' => (Side::Black, PieceType::Rook),
-                            'p' => (Side::Black, PieceType::Pawn),
-                            _ => panic!("from_fen error!: invalidcharacter!"),
-                        };
-                        chessboard.mailbox[square] = Some(piece_data);
-                    } else if s.is_ascii_digit() {
-                        j += (s.to_digit(10).unwrap() as usize) - 1;
-                        //break
-                    } else {
-                        panic!("from_fen error: invalid char in piece placement portion!")
-                    }
-                    k += 1;
-                }
-                j += 1;
+        // parse piece placement data, ranks are listed from 8 to 1 and files from a to h
+        let mut rank: usize = 7;
+        let mut file: usize = 0;
+        for s in input_vec[0].chars() {
+            if s == '/' {
+                assert!(rank > 0 && file == 8, "from_fen error: invalid rank in piece placement!");
+                rank -= 1;
+                file = 0;
+            } else if s.is_ascii_digit() {
+                file += s.to_digit(10).unwrap() as usize;
+            } else if s.is_ascii_alphabetic() {
+                assert!(file < 8, "from_fen error: too many squares in rank!");
+                let square: usize = 8 * rank + (7 - file);
+                chessboard.piece_bbs[sym_index(s)].data |= 1u64 << square;
+                file += 1;
+            } else {
+                panic!("from_fen error: invalid char in piece placement portion!")
             }
-            i += 1
         }
+        chessboard.mailbox = generate_mailbox(chessboard.piece_bbs);
         // parse active colour
         chessboard.side_to_move = match input_vec[1] {
             "w" => Side::White,
@@ -472,7 +415,7 @@ impl ChessBoard {
             _ => panic!("from_fen error: invalid active side!"),
         };
 
-        i = 0;
+        let mut i: usize = 0;
         // parse castling information
         while i < input_vec[2].len() {
             let s = match input_vec[2].chars().nth(i) {
@@ -481,7 +424,7 @@ impl ChessBoard {
             };
 
             match s {
-                '-' => continue,
+                '-' => {}
                 'K' => chessboard.castle_bools[0] = true,
                 'Q' => chessboard.castle_bools[1] = true,
                 'k' => chessboard.castle_bools[2] = true,
@@ -560,10 +503,72 @@ impl ChessBoard {
             }
         }
         chessboard.current_hash = ZH::hash(&chessboard) as u64;
-        chessboard.repeats[chessboard.current_hash as usize] += 1;
+        (chessboard.pst_score, chessboard.phase) = chessboard.compute_pst();
+        chessboard.pawn_hash = ZH::pawn_hash(&chessboard.piece_bbs);
         return chessboard;
     }
 
+    pub fn to_fen(&self) -> String {
+        // placement, squares are visited from a8 (63) down to h1 (0)
+        let mut placement = String::new();
+        let mut empty: usize = 0;
+        let mut square: usize = 64;
+        while square > 0 {
+            square -= 1;
+            let mut j: usize = 0;
+            while j < 12 && self.piece_bbs[j].data & (1 << square) == 0 {
+                j += 1;
+            }
+            if j < 12 {
+                if empty > 0 {
+                    placement.push_str(empty.to_string().as_str());
+                    empty = 0;
+                }
+                placement.push(ASCII_SYM[j]);
+            } else {
+                empty += 1;
+            }
+            if square.is_multiple_of(8) {
+                if empty > 0 {
+                    placement.push_str(empty.to_string().as_str());
+                    empty = 0;
+                }
+                if square > 0 {
+                    placement.push('/');
+                }
+            }
+        }
+
+        let side = match self.side_to_move {
+            Side::White => "w",
+            Side::Black => "b",
+        };
+        let mut castle = String::new();
+        let mut i: usize = 0;
+        while i < 4 {
+            if self.castle_bools[i] {
+                castle.push(['K', 'Q', 'k', 'q'][i]);
+            }
+            i += 1;
+        }
+        if castle.is_empty() {
+            castle.push('-');
+        }
+        let enpassant = match self.enpassant_bb.lsb_index() {
+            Some(x) => SQUARE_SYM[x],
+            None => "-",
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            side,
+            castle,
+            enpassant,
+            self.half_move_clock,
+            self.full_move_counter.max(1)
+        )
+    }
+
     pub const fn blockers(&self) -> BB {
         let mut i = 0;
         let mut data: u64 = 0;
@@ -714,6 +719,85 @@ impl ChessBoard {
         self.is_square_attacked(square, self.side_to_move.update())
     }
 
+    // note: a position can only repeat every second ply, and never across an irreversible move.
+    //       repetitions within the last `ply` plies (the search path) are a draw right away,
+    //       positions from before that have to have occurred twice already.
+    pub const fn is_repetition(&self, ply: usize) -> bool {
+        let limit = if self.history_len < HISTORY_SIZE { self.history_len } else { HISTORY_SIZE };
+        let mut count: usize = 0;
+        let mut distance: usize = 2;
+        while distance <= limit {
+            let index = (self.history_len - distance) % HISTORY_SIZE;
+            if self.hash_history[index] == self.current_hash {
+                if distance <= ply {
+                    return true;
+                }
+                count += 1;
+                if count >= 2 {
+                    return true;
+                }
+            }
+            distance += 2;
+        }
+        false
+    }
+
+    // note: checkmate takes precedence over the fifty-move rule
+    pub const fn is_fifty_move_draw(&self) -> bool {
+        self.half_move_clock >= 100 && (self.check_bb.data == 0 || self.generate_moves().len() > 0)
+    }
+
+    // K vs K, KB vs K, KN vs K and any number of bishops all on the same square colour
+    pub const fn is_insufficient_material(&self) -> bool {
+        let heavies = self.piece_bbs[1].data
+            | self.piece_bbs[4].data
+            | self.piece_bbs[5].data
+            | self.piece_bbs[7].data
+            | self.piece_bbs[10].data
+            | self.piece_bbs[11].data;
+        if heavies != 0 {
+            return false;
+        }
+
+        let knights = self.piece_bbs[2].data | self.piece_bbs[8].data;
+        let bishops = self.piece_bbs[3].data | self.piece_bbs[9].data;
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+        knights == 0 && (bishops & LIGHT_SQUARES.data == 0 || bishops & !LIGHT_SQUARES.data == 0)
+    }
+
+    pub const fn is_draw(&self, ply: usize) -> bool {
+        self.is_repetition(ply) || self.is_insufficient_material() || self.is_fifty_move_draw()
+    }
+
+    // note: en passant is the only capture that does not land on the captured piece
+    pub const fn is_capture(&self, chess_move: ChessMove) -> bool {
+        match chess_move.get_move_type() {
+            MoveType::EnPassant => true,
+            MoveType::Castle => false,
+            _ => self.mailbox[chess_move.target()].is_some(),
+        }
+    }
+
+    // moves that do not change the material balance
+    pub const fn is_quiet(&self, chess_move: ChessMove) -> bool {
+        !self.is_capture(chess_move) && !matches!(chess_move.get_move_type(), MoveType::Promotion)
+    }
+
+    // true if side has anything besides its king and pawns
+    pub const fn has_non_pawn_material(&self, side: Side) -> bool {
+        let i = match side {
+            Side::White => 0,
+            Side::Black => 6,
+        };
+        (self.piece_bbs[i + 1].data
+            | self.piece_bbs[i + 2].data
+            | self.piece_bbs[i + 3].data
+            | self.piece_bbs[i + 4].data)
+            != 0
+    }
+
     pub const fn const_clone(&self) -> ChessBoard {
         ChessBoard {
             piece_bbs: self.piece_bbs,
@@ -725,8 +809,11 @@ impl ChessBoard {
             full_move_counter: self.full_move_counter,
             check_bb: self.check_bb,
             current_hash: self.current_hash,
-            repeats: self.repeats,
-            pv: self.pv,
+            pst_score: self.pst_score,
+            phase: self.phase,
+            pawn_hash: self.pawn_hash,
+            hash_history: self.hash_history,
+            history_len: self.history_len,
         }
     }
 
@@ -1484,12 +1571,15 @@ impl ChessBoard {
                 //update hash
                 chessboard.current_hash ^= ZH::get_piece_hash(source, source_data);
                 chessboard.current_hash ^= ZH::get_piece_hash(target, source_data);
+                chessboard.remove_piece_eval(source, source_data);
+                chessboard.add_piece_eval(target, source_data);
 
                 // if target is occupied, deal with piece capture
                 if let Some(target_data) = chessboard.mailbox[target] {
                     chessboard.piece_bbs[cpt_index(target_data)].data &= !(1 << target);
                     //update hash
                     chessboard.current_hash ^= ZH::get_piece_hash(target, target_data);
+                    chessboard.remove_piece_eval(target, target_data);
                     match target_data {
                         cpt!(R) => {
                             if target == 0 {
@@ -1523,6 +1613,8 @@ impl ChessBoard {
                 //update hash
                 chessboard.current_hash ^= ZH::get_piece_hash(source, source_data);
                 chessboard.current_hash ^= ZH::get_piece_hash(target, source_data);
+                chessboard.remove_piece_eval(source, source_data);
+                chessboard.add_piece_eval(target, source_data);
 
                 // update mailbox
                 chessboard.mailbox[source] = None;
@@ -1542,6 +1634,8 @@ impl ChessBoard {
                         //update hash
                         chessboard.current_hash ^= ZH::get_piece_hash(00, cpt!(R));
                         chessboard.current_hash ^= ZH::get_piece_hash(02, cpt!(R));
+                        chessboard.remove_piece_eval(0, cpt!(R));
+                        chessboard.add_piece_eval(2, cpt!(R));
                     }
 
                     // white queen-side castle
@@ -1555,7 +1649,9 @@ impl ChessBoard {
 
                         //update hash
                         chessboard.current_hash ^= ZH::get_piece_hash(07, cpt!(R));
-                        chessboard.current_hash ^= ZH::get_piece_hash(02, cpt!(R));
+                        chessboard.current_hash ^= ZH::get_piece_hash(04, cpt!(R));
+                        chessboard.remove_piece_eval(7, cpt!(R));
+                        chessboard.add_piece_eval(4, cpt!(R));
                     }
 
                     // black king-side castle
@@ -1570,6 +1666,8 @@ impl ChessBoard {
                         //update hash
                         chessboard.current_hash ^= ZH::get_piece_hash(56, cpt!(r));
                         chessboard.current_hash ^= ZH::get_piece_hash(58, cpt!(r));
+                        chessboard.remove_piece_eval(56, cpt!(r));
+                        chessboard.add_piece_eval(58, cpt!(r));
                     }
 
                     (Side::Black, 61) => {
@@ -1583,6 +1681,8 @@ impl ChessBoard {
                         //update hash
                         chessboard.current_hash ^= ZH::get_piece_hash(63, cpt!(r));
                         chessboard.current_hash ^= ZH::get_piece_hash(60, cpt!(r));
+                        chessboard.remove_piece_eval(63, cpt!(r));
+                        chessboard.add_piece_eval(60, cpt!(r));
                     }
 
                     _ => panic!("update_state error: invalid castling target!"),
@@ -1600,6 +1700,8 @@ impl ChessBoard {
                 //update hash
                 chessboard.current_hash ^= ZH::get_piece_hash(source, source_data);
                 chessboard.current_hash ^= ZH::get_piece_hash(target, source_data);
+                chessboard.remove_piece_eval(source, source_data);
+                chessboard.add_piece_eval(target, source_data);
 
                 let index = match self.side_to_move {
                     Side::White => 11usize,
@@ -1645,6 +1747,7 @@ impl ChessBoard {
 
                 //update hash
                 chessboard.current_hash ^= ZH::get_piece_hash(square, square_data);
+                chessboard.remove_piece_eval(square, square_data);
 
                 // update mailbox
                 chessboard.mailbox[source] = None;
@@ -1670,6 +1773,8 @@ impl ChessBoard {
                 //update hash
                 chessboard.current_hash ^= ZH::get_piece_hash(source, source_data);
                 chessboard.current_hash ^= ZH::get_piece_hash(target, new_piece);
+                chessboard.remove_piece_eval(source, source_data);
+                chessboard.add_piece_eval(target, new_piece);
 
                 // if target is occupied, deal with piece capture
                 if let Some(data_target) = chessboard.mailbox[target] {
@@ -1677,6 +1782,25 @@ impl ChessBoard {
 
                     //update hash
                     chessboard.current_hash ^= ZH::get_piece_hash(target, data_target);
+                    chessboard.remove_piece_eval(target, data_target);
+                    match data_target {
+                        cpt!(R) => {
+                            if target == 0 {
+                                chessboard.castle_bools[0] = false;
+                            } else if target == 7 {
+                                chessboard.castle_bools[1] = false
+                            }
+                        }
+
+                        cpt!(r) => {
+                            if target == 56 {
+                                chessboard.castle_bools[2] = false;
+                            } else if target == 63 {
+                                chessboard.castle_bools[3] = false
+                            }
+                        }
+                        _ => {}
+                    }
                 }
 
                 // update mailbox
@@ -1691,7 +1815,20 @@ impl ChessBoard {
             _____ => {}
         }
         chessboard.side_to_move = chessboard.side_to_move.update();
-        chessboard.half_move_clock += 1;
+
+        // pawn moves and captures are irreversible, no earlier position can occur again
+        let is_irreversible = match source_data {
+            cpt!(P) | cpt!(p) => true,
+            _ => self.mailbox[target].is_some(),
+        };
+        if is_irreversible {
+            chessboard.half_move_clock = 0;
+            chessboard.history_len = 0;
+        } else {
+            chessboard.half_move_clock += 1;
+            chessboard.hash_history[self.history_len % HISTORY_SIZE] = self.current_hash;
+            chessboard.history_len = self.history_len + 1;
+        }
 
         // ['K','Q','N','B','R','P','k','q','n','b','r','p'];
         //check if move results in opponent's king to be in check
@@ -1754,28 +1891,17 @@ impl ChessBoard {
             }
         }
 
-        let mut enpassant_bb = chessboard.enpassant_bb;
-        while enpassant_bb.data != 0 {
-            let square = match enpassant_bb.lsb_index() {
-                Some(x) => x,
-                None => unreachable!(),
-            };
-            chessboard.current_hash ^= ZH_KEYS.1[4 + COLS[square]];
-            enpassant_bb = enpassant_bb.pop_bit(square);
-        }
-
-        // lazy way to handle this
+        //castling hash, remove the old rights and add the new ones
         let mut i: usize = 0;
-        //castling hash
         while i < 4 {
-            if chessboard.castle_bools[i] {
+            if self.castle_bools[i] != chessboard.castle_bools[i] {
                 chessboard.current_hash ^= ZH_KEYS.1[i];
             }
             i += 1;
         }
 
-        //en passant hash
-        let mut enpassant_bb = chessboard.enpassant_bb;
+        //en passant hash, remove the old square and add the new one
+        let mut enpassant_bb = BB { data: self.enpassant_bb.data ^ chessboard.enpassant_bb.data };
         while enpassant_bb.data != 0 {
             let square = match enpassant_bb.lsb_index() {
                 Some(x) => x,
@@ -1788,38 +1914,58 @@ impl ChessBoard {
         //side to move hash
         chessboard.current_hash ^= ZH_KEYS.2[0];
 
-        chessboard.repeats[((self.current_hash % usize::MAX as u64) as usize) % (1 << 14)] += 1;
+        return chessboard;
+    }
+
+    // passes the turn to the opponent, used by null-move pruning
+    // note: must not be called while in check. repetitions are not looked for across a null
+    //       move, since the positions before it can not actually occur again.
+    pub const fn make_null_move(&self) -> ChessBoard {
+        let mut chessboard = self.const_clone();
+        if self.enpassant_bb.data != 0 {
+            let square = match self.enpassant_bb.lsb_index() {
+                Some(x) => x,
+                None => unreachable!(),
+            };
+            chessboard.current_hash ^= ZH_KEYS.1[4 + COLS[square]];
+            chessboard.enpassant_bb = BB::ZERO;
+        }
+        match chessboard.side_to_move {
+            Side::Black => chessboard.full_move_counter += 1,
+            Side::White => {}
+        }
+        chessboard.side_to_move = chessboard.side_to_move.update();
+        chessboard.current_hash ^= ZH_KEYS.2[0];
+        chessboard.half_move_clock += 1;
+        chessboard.history_len = 0;
+        chessboard.check_bb = BB::ZERO;
+        chessboard
+    }
 
-        //move principal variation forward
-        if self.pv.len() > 0 {
-            chessboard.pv.count = self.pv.count - 1;
-            chessboard.pv.data = [None; 256];
-            let mut i: usize = 0;
-            while i + 1 < self.pv.count {
-                chessboard.pv.data[i] = self.pv.data[i + 1];
-                i += 1;
+    // the legal move written as move_str in uci notation, if there is one
+    pub fn find_uci_move(&self, move_str: &str) -> Option<ChessMove> {
+        let moves_arr = self.generate_moves();
+        let mut i: usize = 0;
+        while i < moves_arr.len() {
+            let chess_move: ChessMove = moves_arr.data[i].unwrap();
+            if format!("{}", chess_move) == move_str {
+                //maybe parse into a source/target and do int compare
+                return Some(chess_move);
             }
+            i += 1;
         }
-        return chessboard;
+        None
     }
 
     pub fn parse_uci_move(&mut self, moves_str: &str) {
         let chess_moves = moves_str.split(' ');
         for uci_moves in chess_moves {
-            let moves_arr = self.generate_moves();
             if uci_moves == "startpos" {
                 *self = ChessBoard::default();
                 continue;
             }
-            let mut i: usize = 0;
-            while i < moves_arr.len() {
-                let chess_move: ChessMove = moves_arr.data[i].unwrap();
-                if format!("{}", chess_move) == uci_moves {
-                    //maybe parse into a source/target and do int compare
-                    *self = self.update_state(chess_move);
-                    break;
-                }
-                i += 1;
+            if let Some(chess_move) = self.find_uci_move(uci_moves) {
+                *self = self.update_state(chess_move);
             }
         }
     }
@@ -1844,12 +1990,22 @@ impl ChessBoard {
             // UCI command - fen
             if cmd == "FEN" || cmd == "fen" {
                 //todo:fix from_fen command;
-                let fen: &str = match cmds.next() {
-                    Some(x) => x,
-                    None => INITIAL_CHESS_POS_FEN,
-                };
+                let mut fields: Vec<&str> = Vec::new();
+                while fields.len() < 6 {
+                    match cmds.next() {
+                        Some("moves") | None => break,
+                        Some(x) => fields.push(x),
+                    }
+                }
+                // some guis leave out the move clocks
+                if fields.len() == 4 {
+                    fields.extend(["0", "1"]);
+                }
 
-                *self = ChessBoard::from_fen(fen);
+                *self = match fields.len() {
+                    0 => ChessBoard::from_fen(INITIAL_CHESS_POS_FEN),
+                    _ => ChessBoard::from_fen(fields.join(" ").as_str()),
+                };
                 continue;
             }
             // UCI command - moves
@@ -1870,25 +2026,8 @@ impl ChessBoard {
         }
     }
     pub fn parse_uci_go_cmd(&mut self, cmd_str: &str) -> String {
-        let mut depth: usize = 6;
-
-        let mut cmds = cmd_str.split(' ');
-
-        while let Some(cmd) = cmds.next() {
-            // fix depth search
-            if cmd == "depth" {
-                //todo: fix this lazy shit
-                depth =
-                    cmds.next().expect("Invalid input").parse::<usize>().expect("Invalid input");
-            }
-            // other cases placeholder
-            else {
-                depth = 6;
-            }
-            //other cases?
-        }
-        //search_position(depth)
-        format!("bestmove {}", self.search(depth))
+        let limits = SearchLimits::from_uci(cmd_str, self);
+        self.search(&limits).to_uci()
     }
 }
 
@@ -2067,6 +2206,22 @@ impl ZorbristHash {
         (val % (usize::MAX as u64)) as usize
     }
 
+    // note: the keys are those of the pawns in the full hash
+    pub const fn pawn_hash(piece_bbs: &[BB; 12]) -> u64 {
+        let mut val = 0u64;
+        let mut i = 0usize;
+        while i < 64 {
+            if piece_bbs[5].data & (1u64 << i) != 0 {
+                val ^= ZH_KEYS.0[i][5];
+            }
+            if piece_bbs[11].data & (1u64 << i) != 0 {
+                val ^= ZH_KEYS.0[i][11];
+            }
+            i += 1;
+        }
+        val
+    }
+
     pub const fn get_piece_hash(square: usize, piece_type: (Side, PieceType)) -> u64 {
         ZH_KEYS.0[square][cpt_index(piece_type)]
     }
@@ -239,11 +239,21 @@ pub struct MovesArray {
     pub count: usize,
 }
 
+impl Default for MovesArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MovesArray {
     pub const fn new() -> Self {
         Self { data: [None; 256], count: 0 }
     }
 
+    pub fn contains(&self, chess_move: ChessMove) -> bool {
+        self.data[..self.count].contains(&Some(chess_move))
+    }
+
     pub const fn new_add(&self, chess_move: ChessMove) -> MovesArray {
         let mut data = self.data;
         data[self.count] = Some(chess_move);
@@ -0,0 +1,284 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::score::*;
+use crate::search::*;
+use crate::ttable::*;
+use crate::SEARCH_STACK_SIZE;
+
+/* training data from self-play, for the tuner and for nnue training.
+
+every game starts from a few random moves off the start position, then both sides play
+the best move of a fixed node search. a position is written out if the side to move is not
+in check and the best move is quiet (no capture or promotion) and not a mate. one line per
+position:
+    <fen> | <score> | <result>
+with the score of the search in centipawns and the result of the game (1.0, 0.5 or 0.0),
+both from white's point of view. the tuner reads these files as they are.
+
+note: a game only depends on the seed and its number, not on the thread that plays it:
+      every game has its own rng and an empty hash, and searches single threaded. games
+      are written in order of their number, so the output is the same for any number of
+      threads. */
+
+pub const DATAGEN_GAMES: u64 = 100;
+pub const DATAGEN_NODES: u64 = 5000;
+pub const DATAGEN_OUTPUT: &str = "datagen.txt";
+// random plies at the start of every game, and then sometimes one more
+pub const RANDOM_PLIES: usize = 8;
+// openings the search thinks are more lopsided than this are played again
+pub const MAX_OPENING_SCORE: i32 = 400;
+// a game is won once the score stays above this for ADJUDICATION_PLIES plies
+pub const WIN_SCORE: i32 = 2000;
+pub const ADJUDICATION_PLIES: usize = 4;
+// and drawn after this many plies
+pub const MAX_GAME_PLIES: usize = 400;
+const DATAGEN_HASH_MB: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatagenConfig {
+    pub games: u64,
+    pub threads: usize,
+    pub seed: u64,
+    pub nodes: u64,
+    pub output: String,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        DatagenConfig {
+            games: DATAGEN_GAMES,
+            threads: 1,
+            seed: 0,
+            nodes: DATAGEN_NODES,
+            output: DATAGEN_OUTPUT.to_string(),
+        }
+    }
+}
+
+impl DatagenConfig {
+    // "games <n> threads <n> seed <n> nodes <n> output <file>", in any order, anything
+    // missing or malformed stays at its default
+    pub fn from_args(args: &[&str]) -> DatagenConfig {
+        let mut config = DatagenConfig::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().copied().unwrap_or("");
+            match *arg {
+                "games" => config.games = value.parse().unwrap_or(config.games),
+                "threads" => {
+                    config.threads = value.parse().unwrap_or(config.threads).clamp(1, MAX_THREADS)
+                }
+                "seed" => config.seed = value.parse().unwrap_or(config.seed),
+                "nodes" => config.nodes = value.parse().unwrap_or(config.nodes).max(1),
+                "output" if !value.is_empty() => config.output = value.to_string(),
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+// a few random legal moves off the start position
+fn random_opening(rng: &mut StdRng) -> ChessBoard {
+    loop {
+        let mut chessboard = ChessBoard::default();
+        let plies = RANDOM_PLIES + rng.gen_range(0..2);
+        let mut ply: usize = 0;
+        while ply < plies {
+            let moves = chessboard.generate_moves();
+            if moves.len() == 0 {
+                break;
+            }
+            match moves.data[rng.gen_range(0..moves.len())] {
+                Some(x) => chessboard = chessboard.update_state(x),
+                None => unreachable!(),
+            }
+            ply += 1;
+        }
+        if ply == plies && chessboard.generate_moves().len() > 0 {
+            return chessboard;
+        }
+    }
+}
+
+// plays game number game, returns its output lines
+// note: tt and evaluators are those of the thread, reused from game to game
+pub fn play_game(
+    config: &DatagenConfig,
+    game: u64,
+    tt: &TranspositionTable,
+    evaluators: &mut Vec<HandCraftedEval>,
+) -> Vec<String> {
+    // note: the multiplier spreads consecutive game numbers over the seed space
+    let mut rng = StdRng::seed_from_u64(config.seed ^ game.wrapping_mul(0x9e3779b97f4a7c15));
+    let limits = SearchLimits { nodes: Some(config.nodes), ..SearchLimits::default() };
+    let search_config = SearchConfig { quiet: true, ..SearchConfig::default() };
+    let mut search = |chessboard: &mut ChessBoard| {
+        let signals = Arc::new(SearchSignals::default());
+        chessboard.search_with_evaluators(&limits, &search_config, tt, evaluators, signals)
+    };
+    tt.clear();
+
+    let mut chessboard = random_opening(&mut rng);
+    while search(&mut chessboard.clone()).score.data.abs() > MAX_OPENING_SCORE {
+        chessboard = random_opening(&mut rng);
+    }
+
+    let mut positions: Vec<(String, i32)> = Vec::new();
+    let mut winning_plies: usize = 0;
+    let mut ply: usize = 0;
+    let result = loop {
+        if chessboard.generate_moves().len() == 0 {
+            break match (chessboard.check_bb.data != 0, chessboard.side_to_move) {
+                (false, _) => 0.5,
+                (true, Side::White) => 0.0,
+                (true, Side::Black) => 1.0,
+            };
+        }
+        if chessboard.is_draw(0) || ply >= MAX_GAME_PLIES {
+            break 0.5;
+        }
+
+        let result = search(&mut chessboard.clone());
+        let best_move = match result.best_move {
+            Some(x) => x,
+            None => unreachable!(),
+        };
+        // from white's point of view
+        let score = side_relative(&chessboard, result.score.data).data;
+        if score.abs() >= WIN_SCORE {
+            winning_plies += 1;
+            if winning_plies >= ADJUDICATION_PLIES {
+                break if score > 0 { 1.0 } else { 0.0 };
+            }
+        } else {
+            winning_plies = 0;
+        }
+        if chessboard.check_bb.data == 0
+            && chessboard.is_quiet(best_move)
+            && !result.score.is_mate()
+        {
+            positions.push((chessboard.to_fen(), score));
+        }
+        chessboard = chessboard.update_state(best_move);
+        ply += 1;
+    };
+    positions
+        .into_iter()
+        .map(|(fen, score)| format!("{} | {} | {:.1}", fen, score, result))
+        .collect()
+}
+
+// plays config.games games on config.threads threads and writes the positions to
+// config.output, returns the number of positions written
+pub fn datagen(config: &DatagenConfig) -> io::Result<u64> {
+    let mut output = BufWriter::new(File::create(&config.output)?);
+    let start = Instant::now();
+    let next_game = AtomicU64::new(0);
+    let (sender, receiver) = mpsc::channel::<(u64, Vec<String>)>();
+    let mut positions: u64 = 0;
+
+    thread::scope(|scope| -> io::Result<()> {
+        let mut thread_id: usize = 0;
+        while thread_id < config.threads {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn_scoped(
+                scope,
+                move || {
+                    let tt = TranspositionTable::new(DATAGEN_HASH_MB);
+                    let mut evaluators = vec![HandCraftedEval::default()];
+                    loop {
+                        let game = next_game.fetch_add(1, Ordering::Relaxed);
+                        if game >= config.games {
+                            break;
+                        }
+                        let lines = play_game(config, game, &tt, &mut evaluators);
+                        if sender.send((game, lines)).is_err() {
+                            break;
+                        }
+                    }
+                },
+            );
+            handle?;
+            thread_id += 1;
+        }
+        drop(sender);
+
+        // games finish out of order, they are held back until the ones before are written
+        let mut finished: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+        let mut next_to_write: u64 = 0;
+        for (game, lines) in receiver.iter() {
+            finished.insert(game, lines);
+            while let Some(lines) = finished.remove(&next_to_write) {
+                for line in lines.iter() {
+                    writeln!(output, "{}", line)?;
+                }
+                positions += lines.len() as u64;
+                next_to_write += 1;
+                if next_to_write.is_multiple_of(10) || next_to_write == config.games {
+                    let elapsed = start.elapsed().as_millis() as u64;
+                    println!(
+                        "games {}/{}, positions {}, positions/second {}",
+                        next_to_write,
+                        config.games,
+                        positions,
+                        positions * 1000 / elapsed.max(1)
+                    );
+                }
+            }
+        }
+        Ok(())
+    })?;
+    output.flush()?;
+    println!("wrote {} positions to {}", positions, config.output);
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::parse_tuning_line;
+
+    // runs datagen with threads into a temporary file, and returns what it wrote
+    fn datagen_output(threads: usize) -> (u64, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!("stowockfish_datagen_test_{}.txt", threads));
+        let config = DatagenConfig {
+            games: 4,
+            threads,
+            seed: 7,
+            nodes: 1000,
+            output: path.to_str().unwrap().to_string(),
+        };
+        let positions = datagen(&config).unwrap();
+        let output = std::fs::read(&config.output).unwrap();
+        std::fs::remove_file(&config.output).unwrap();
+        (positions, output)
+    }
+
+    #[test]
+    fn output_does_not_depend_on_the_thread_count() {
+        let (positions, output) = datagen_output(1);
+        assert!(positions > 0);
+        assert_eq!(datagen_output(3), (positions, output.clone()));
+
+        // and the tuner reads every line of it
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text.lines().count() as u64, positions);
+        assert!(text.lines().all(|x| parse_tuning_line(x).is_some()));
+    }
+}
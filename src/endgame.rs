@@ -0,0 +1,363 @@
+#![allow(dead_code)]
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::king::*;
+use crate::pawns::*;
+
+/* endgames the general evaluation gets wrong, looked up by material.
+
+an endgame either replaces the evaluation with its own (the wins against a lone king,
+where the eval has to lead the search to the mate) or scales it down towards a draw
+(material that looks winning but is not). exact material signatures such as "KBNK" are
+looked up in ENDGAMES, the ones that allow any number of pawns or pieces are checked after
+that, in probe_endgame.
+
+note: signatures list the pieces of the strong side after the first K and those of the
+      weak side after the second, both sides are looked up. */
+
+pub const SCALE_NORMAL: i32 = 64;
+// a won ending, less than a mate but more than any normal evaluation
+pub const KNOWN_WIN: i32 = 10000;
+
+#[derive(Debug, Copy, Clone)]
+pub enum EndgameKind {
+    // evaluation of the position from the strong side's point of view
+    Value(fn(&ChessBoard, Side) -> i32),
+    // scale factor for the evaluation when the strong side is ahead, out of SCALE_NORMAL
+    Scale(fn(&ChessBoard, Side) -> i32),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Endgame {
+    pub name: &'static str,
+    pub kind: EndgameKind,
+}
+
+pub const ENDGAMES: [Endgame; 4] = [
+    Endgame { name: "KQK", kind: EndgameKind::Value(lone_king) },
+    Endgame { name: "KRK", kind: EndgameKind::Value(lone_king) },
+    Endgame { name: "KBNK", kind: EndgameKind::Value(bishop_knight_mate) },
+    Endgame { name: "KNNK", kind: EndgameKind::Scale(draw) },
+];
+// material keys of ENDGAMES with white and with black as the strong side
+const ENDGAME_KEYS: [[u64; 2]; 4] = init_endgame_keys();
+
+// a lone king against enough material to mate, with or without pawns
+pub const KXK: Endgame = Endgame { name: "KXK", kind: EndgameKind::Value(lone_king) };
+// bishop and pawns on one rook file, the bishop not covering the promotion square
+pub const KBPSK: Endgame = Endgame { name: "KBPsK", kind: EndgameKind::Scale(wrong_bishop) };
+// a bishop each on different colours, and pawns
+pub const OPPOSITE_BISHOPS: Endgame =
+    Endgame { name: "KBPsKBPs", kind: EndgameKind::Scale(opposite_bishops) };
+
+/* material keys: the number of Q,N,B,R,P of white and then of black, four bits each. */
+
+// material key of piece_bbs
+pub const fn material_key(piece_bbs: &[BB; 12]) -> u64 {
+    let mut key: u64 = 0;
+    let mut i: usize = 0;
+    while i < 10 {
+        // skip the kings
+        let piece = 1 + i + i / 5;
+        key |= (piece_bbs[piece].data.count_ones() as u64) << (4 * i);
+        i += 1;
+    }
+    key
+}
+
+// material key of a signature such as "KBNK", with strong as the side of the first king
+pub const fn signature_key(signature: &str, strong: Side) -> u64 {
+    let bytes = signature.as_bytes();
+    let mut key: u64 = 0;
+    let mut kings: usize = 0;
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let piece = match bytes[i] {
+            b'K' => {
+                kings += 1;
+                i += 1;
+                continue;
+            }
+            b'Q' => 0,
+            b'N' => 1,
+            b'B' => 2,
+            b'R' => 3,
+            b'P' => 4,
+            _ => panic!("signature_key error: invalid piece!"),
+        };
+        // kings is 1 for the strong side and 2 for the weak side
+        let side = match strong {
+            Side::White => kings - 1,
+            Side::Black => 2 - kings,
+        };
+        key += 1 << (4 * (piece + 5 * side));
+        i += 1;
+    }
+    key
+}
+
+const fn init_endgame_keys() -> [[u64; 2]; 4] {
+    let mut keys = [[0; 2]; 4];
+    let mut i: usize = 0;
+    while i < ENDGAMES.len() {
+        keys[i][0] = signature_key(ENDGAMES[i].name, Side::White);
+        keys[i][1] = signature_key(ENDGAMES[i].name, Side::Black);
+        i += 1;
+    }
+    keys
+}
+
+const fn side_material(chessboard: &ChessBoard, side: Side) -> (u32, u32) {
+    let o = 6 * side as usize;
+    let mut pieces: u32 = 0;
+    let mut i: usize = 1;
+    while i < 5 {
+        pieces += chessboard.piece_bbs[o + i].data.count_ones();
+        i += 1;
+    }
+    (pieces, chessboard.piece_bbs[o + 5].data.count_ones())
+}
+
+// a queen, a rook, bishop and knight, or bishops on both colours
+const fn can_mate(chessboard: &ChessBoard, side: Side) -> bool {
+    let o = 6 * side as usize;
+    let bishops = chessboard.piece_bbs[o + 3].data;
+    chessboard.piece_bbs[o + 1].data | chessboard.piece_bbs[o + 4].data != 0
+        || (bishops != 0 && chessboard.piece_bbs[o + 2].data != 0)
+        || (bishops & LIGHT_SQUARES.data != 0 && bishops & !LIGHT_SQUARES.data != 0)
+}
+
+// the endgame of the position and its strong side, None for symmetric endgames
+pub fn probe_endgame(chessboard: &ChessBoard) -> Option<(Endgame, Option<Side>)> {
+    let key = material_key(&chessboard.piece_bbs);
+    let mut i: usize = 0;
+    while i < ENDGAMES.len() {
+        if key == ENDGAME_KEYS[i][0] {
+            return Some((ENDGAMES[i], Some(Side::White)));
+        }
+        if key == ENDGAME_KEYS[i][1] {
+            return Some((ENDGAMES[i], Some(Side::Black)));
+        }
+        i += 1;
+    }
+
+    let white = side_material(chessboard, Side::White);
+    let black = side_material(chessboard, Side::Black);
+    for (strong, weak) in [(Side::White, black), (Side::Black, white)] {
+        if weak != (0, 0) {
+            continue;
+        }
+        if can_mate(chessboard, strong) {
+            return Some((KXK, Some(strong)));
+        }
+        let o = 6 * strong as usize;
+        let strong_material = match strong {
+            Side::White => white,
+            Side::Black => black,
+        };
+        if strong_material.0 == 1 && chessboard.piece_bbs[o + 3].data != 0 {
+            return Some((KBPSK, Some(strong)));
+        }
+    }
+
+    let bishops = (chessboard.piece_bbs[3].data, chessboard.piece_bbs[9].data);
+    if white.0 == 1
+        && black.0 == 1
+        && bishops.0 != 0
+        && bishops.1 != 0
+        && (bishops.0 & LIGHT_SQUARES.data == 0) != (bishops.1 & LIGHT_SQUARES.data == 0)
+    {
+        return Some((OPPOSITE_BISHOPS, None));
+    }
+    None
+}
+
+// the evaluation from white's point of view, the tapered total corrected by its endgame
+pub fn endgame_value(chessboard: &ChessBoard, total: TaperedScore) -> i32 {
+    let value = total.taper(chessboard.phase);
+    let (endgame, strong) = match probe_endgame(chessboard) {
+        Some(x) => x,
+        None => return value,
+    };
+    let ahead = if value >= 0 { Side::White } else { Side::Black };
+    match (endgame.kind, strong) {
+        (EndgameKind::Value(f), Some(side)) => match side {
+            Side::White => f(chessboard, side),
+            Side::Black => -f(chessboard, side),
+        },
+        // only the side ahead is scaled down
+        (EndgameKind::Scale(f), Some(side)) if side == ahead => {
+            value * f(chessboard, side) / SCALE_NORMAL
+        }
+        (EndgameKind::Scale(f), None) => value * f(chessboard, ahead) / SCALE_NORMAL,
+        _ => value,
+    }
+}
+
+/* the endgame functions, all from the strong side's point of view. */
+
+// endgame value of side's material, pawns included
+const fn material(chessboard: &ChessBoard, side: Side) -> i32 {
+    let o = 6 * side as usize;
+    let mut value: i32 = 0;
+    let mut i: usize = 1;
+    while i < 6 {
+        value +=
+            DEFAULT_PARAMS.material[i].eg * chessboard.piece_bbs[o + i].data.count_ones() as i32;
+        i += 1;
+    }
+    value
+}
+
+// from 0 in the centre to 120 in the corners
+const fn push_to_edge(square: usize) -> i32 {
+    let col = COLS[square] as i32;
+    let row = ROWS[square] as i32;
+    let file_distance = if col < 4 { 3 - col } else { col - 4 };
+    let rank_distance = if row < 4 { 3 - row } else { row - 4 };
+    20 * (file_distance + rank_distance)
+}
+
+// from 0 with the kings far apart to 120 with the kings next to each other
+const fn push_close(a: usize, b: usize) -> i32 {
+    20 * (7 - distance(a, b))
+}
+
+// drive the lone king to the edge, and bring the strong king to it
+pub fn lone_king(chessboard: &ChessBoard, strong: Side) -> i32 {
+    let strong_king = king_square(chessboard, strong);
+    let weak_king = king_square(chessboard, strong.update());
+    KNOWN_WIN
+        + material(chessboard, strong)
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+// the mate only works in a corner of the bishop's colour, drive the king there
+pub fn bishop_knight_mate(chessboard: &ChessBoard, strong: Side) -> i32 {
+    let strong_king = king_square(chessboard, strong);
+    let weak_king = king_square(chessboard, strong.update());
+    let bishops = chessboard.piece_bbs[6 * strong as usize + 3].data;
+    // from 0 on the diagonal between the wrong corners to 7 in the right ones, so that the
+    // king is also driven out of a wrong corner. h1 and a8 are light, a1 and h8 are dark.
+    let col = COLS[weak_king] as i32;
+    let row = ROWS[weak_king] as i32;
+    let push_to_corner = match bishops & LIGHT_SQUARES.data != 0 {
+        true => (7 - col - row).abs(),
+        false => (col - row).abs(),
+    };
+    KNOWN_WIN
+        + material(chessboard, strong)
+        + 400 * push_to_corner
+        + push_close(strong_king, weak_king)
+}
+
+pub fn draw(_chessboard: &ChessBoard, _strong: Side) -> i32 {
+    0
+}
+
+// a rook pawn whose promotion square the bishop can not cover, with the lone king in front
+// of it, is a draw however many of them there are
+pub fn wrong_bishop(chessboard: &ChessBoard, strong: Side) -> i32 {
+    let o = 6 * strong as usize;
+    let pawns = chessboard.piece_bbs[o + 5].data;
+    let bishops = chessboard.piece_bbs[o + 3].data;
+    for file in [0, 7] {
+        if pawns & !FILE_MASKS[file].data != 0 {
+            continue;
+        }
+        let promotion = promotion_square(strong, file);
+        let light_promotion = LIGHT_SQUARES.data & (1u64 << promotion) != 0;
+        let light_bishop = bishops & LIGHT_SQUARES.data != 0;
+        let weak_king = king_square(chessboard, strong.update());
+        if light_promotion != light_bishop && distance(weak_king, promotion) <= 1 {
+            return 0;
+        }
+    }
+    SCALE_NORMAL
+}
+
+// drawish, unless the strong side has passed pawns
+pub fn opposite_bishops(chessboard: &ChessBoard, strong: Side) -> i32 {
+    let s = strong as usize;
+    let own = chessboard.piece_bbs[6 * s + 5].data;
+    let enemy = chessboard.piece_bbs[11 - 6 * s].data;
+    let mut passed: i32 = 0;
+    let mut pawns = BB { data: own };
+    while let Some(square) = pawns.lsb_index() {
+        pawns = pawns.pop_bit(square);
+        if enemy & PASSED_MASKS[s][square].data == 0 {
+            passed += 1;
+        }
+    }
+    (16 + 12 * passed).min(SCALE_NORMAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same position with the colours swapped and the board flipped
+    fn mirrored(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split_ascii_whitespace().collect();
+        let swap_case = |x: char| match x.is_ascii_uppercase() {
+            true => x.to_ascii_lowercase(),
+            false => x.to_ascii_uppercase(),
+        };
+        let ranks: Vec<String> =
+            fields[0].split('/').rev().map(|x| x.chars().map(swap_case).collect()).collect();
+        let side = match fields[1] {
+            "w" => "b",
+            _ => "w",
+        };
+        format!("{} {} {}", ranks.join("/"), side, fields[2..].join(" "))
+    }
+
+    // the eval of fen from white's point of view
+    fn white_eval(fen: &str) -> i32 {
+        let chessboard = ChessBoard::from_fen(fen);
+        let score = HandCraftedEval::default().evaluate(&chessboard);
+        side_relative(&chessboard, score.data).data
+    }
+
+    #[test]
+    fn lone_king_endings_are_known_wins() {
+        let fens = [
+            "8/8/8/4k3/8/8/8/KQ6 w - - 0 1",
+            "8/8/8/4k3/8/8/8/KQ6 b - - 0 1",
+            "8/8/8/4k3/8/8/8/KR6 w - - 0 1",
+            "8/8/8/4k3/8/8/8/KR6 b - - 0 1",
+            "8/8/8/4k3/8/8/8/KBN5 w - - 0 1",
+            "8/8/8/4k3/8/8/8/KBN5 b - - 0 1",
+        ];
+        for fen in fens {
+            assert!(white_eval(fen) >= KNOWN_WIN, "{}", fen);
+            assert!(white_eval(&mirrored(fen)) <= -KNOWN_WIN, "{}", mirrored(fen));
+        }
+    }
+
+    #[test]
+    fn bishop_and_knight_drive_to_the_bishops_corner() {
+        // the bishop on b1 is light, so a8 is a mating corner and h8 is not
+        let right = "k7/8/8/8/4K3/8/8/1BN5 w - - 0 1";
+        let wrong = "7k/8/8/8/3K4/8/8/1BN5 w - - 0 1";
+        assert!(white_eval(right) > white_eval(wrong));
+        assert!(white_eval(&mirrored(right)) < white_eval(&mirrored(wrong)));
+    }
+
+    #[test]
+    fn wrong_bishop_and_rook_pawn_is_a_draw() {
+        // the dark bishop on c1 can not cover a8, which the black king holds
+        let fens = ["k7/8/8/8/8/P7/8/K1B5 w - - 0 1", "k7/8/8/8/8/P7/8/K1B5 b - - 0 1"];
+        for fen in fens {
+            assert_eq!(white_eval(fen), 0, "{}", fen);
+            assert_eq!(white_eval(&mirrored(fen)), 0, "{}", mirrored(fen));
+        }
+        // with the light bishop on b1 it is still winning
+        let fen = "k7/8/8/8/8/P7/8/KB6 w - - 0 1";
+        assert!(white_eval(fen) > 300, "{}", fen);
+        assert!(white_eval(&mirrored(fen)) < -300, "{}", mirrored(fen));
+    }
+}
@@ -0,0 +1,605 @@
+#![allow(dead_code)]
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::chessmove::*;
+use crate::endgame::*;
+use crate::king::*;
+use crate::pawns::*;
+use crate::pieces::*;
+use crate::score::*;
+
+/* tapered evaluation: every term has a middlegame and an endgame value, which are blended
+by the game phase. the phase is counted from the non-pawn material left on the board, from
+MAX_PHASE with all pieces present (pure middlegame) down to 0 with only kings and pawns
+(pure endgame).
+
+note: the piece-square tables of DEFAULT_PARAMS are updated incrementally by update_state,
+      in the same way as the zobrist hash, so that the static eval costs O(1).
+note: all values are in centipawns, from white's point of view. */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TaperedScore {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl TaperedScore {
+    pub const ZERO: TaperedScore = TaperedScore { mg: 0, eg: 0 };
+
+    pub const fn new(mg: i32, eg: i32) -> TaperedScore {
+        TaperedScore { mg, eg }
+    }
+
+    // note: phases above MAX_PHASE (after promotions) count as the middlegame
+    pub const fn taper(&self, phase: i32) -> i32 {
+        let phase = if phase < MAX_PHASE { phase } else { MAX_PHASE };
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for TaperedScore {
+    type Output = TaperedScore;
+    fn add(self, rhs: TaperedScore) -> Self::Output {
+        TaperedScore { mg: self.mg + rhs.mg, eg: self.eg + rhs.eg }
+    }
+}
+
+impl Sub for TaperedScore {
+    type Output = TaperedScore;
+    fn sub(self, rhs: TaperedScore) -> Self::Output {
+        TaperedScore { mg: self.mg - rhs.mg, eg: self.eg - rhs.eg }
+    }
+}
+
+impl Neg for TaperedScore {
+    type Output = TaperedScore;
+    fn neg(self) -> Self::Output {
+        TaperedScore { mg: -self.mg, eg: -self.eg }
+    }
+}
+
+impl Mul<i32> for TaperedScore {
+    type Output = TaperedScore;
+    fn mul(self, rhs: i32) -> Self::Output {
+        TaperedScore { mg: self.mg * rhs, eg: self.eg * rhs }
+    }
+}
+
+impl AddAssign for TaperedScore {
+    fn add_assign(&mut self, rhs: TaperedScore) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for TaperedScore {
+    fn sub_assign(&mut self, rhs: TaperedScore) {
+        *self = *self - rhs;
+    }
+}
+
+// shorthand for the tables of weights below
+const fn ts(mg: i32, eg: i32) -> TaperedScore {
+    TaperedScore::new(mg, eg)
+}
+
+/* the weights of the evaluation terms.
+
+note: arrays indexed by rank are by the rank relative to the side of the piece, 0 being
+      its own back rank.
+note: the piece-square tables are laid out as the board is seen from white's side, a8 first
+      and h1 last. a white piece on square i uses entry 63 - i, a black piece uses the entry
+      of the square mirrored to white's side. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    // material values of K,Q,N,B,R,P
+    pub material: [TaperedScore; 6],
+    pub king_pst: [TaperedScore; 64],
+    pub queen_pst: [TaperedScore; 64],
+    pub knight_pst: [TaperedScore; 64],
+    pub bishop_pst: [TaperedScore; 64],
+    pub rook_pst: [TaperedScore; 64],
+    pub pawn_pst: [TaperedScore; 64],
+    pub doubled_pawn: TaperedScore,
+    pub isolated_pawn: TaperedScore,
+    pub backward_pawn: TaperedScore,
+    // pawns defended by or side by side with another pawn
+    pub connected_pawn: [TaperedScore; 8],
+    pub passed_pawn: [TaperedScore; 8],
+    // passed pawns with nothing in front of them
+    pub passed_free: [TaperedScore; 8],
+    // per square of king distance to the promotion square, times the rank above the 3rd
+    pub passed_own_king: TaperedScore,
+    pub passed_enemy_king: TaperedScore,
+    // passed pawns outside the square of the enemy king, in pawn endings
+    pub passed_unstoppable: TaperedScore,
+    // by the number of safe squares attacked
+    pub knight_mobility: [TaperedScore; 9],
+    pub bishop_mobility: [TaperedScore; 14],
+    pub rook_mobility: [TaperedScore; 15],
+    pub queen_mobility: [TaperedScore; 28],
+    pub rook_open_file: TaperedScore,
+    pub rook_semi_open_file: TaperedScore,
+    pub rook_on_seventh: TaperedScore,
+    pub bishop_pair: TaperedScore,
+    // knights defended by a pawn that no enemy pawn can attack
+    pub knight_outpost: TaperedScore,
+    // rooks shut in by their own uncastled king, bishops shut in on a7/h7 by a pawn
+    pub trapped_rook: TaperedScore,
+    pub trapped_bishop: TaperedScore,
+    // king danger of K,Q,N,B,R,P: per square of the king zone attacked and per safe check
+    pub king_zone_attack: [TaperedScore; 6],
+    pub king_safe_check: [TaperedScore; 6],
+    // own pawns one and two ranks in front of the king, on its file and the adjacent ones
+    pub pawn_shield: [TaperedScore; 2],
+    // enemy pawns on those files by their rank, relative to the king's side
+    pub pawn_storm: [TaperedScore; 8],
+    pub king_open_file: TaperedScore,
+    pub king_semi_open_file: TaperedScore,
+}
+
+#[rustfmt::skip]
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    material: [ts(0, 0), ts(1025, 936), ts(337, 281), ts(365, 297), ts(477, 512), ts(82, 94)],
+    king_pst: [
+        ts(-65, -74), ts(23, -35), ts(16, -18), ts(-15, -18), ts(-56, -11), ts(-34, 15), ts(2, 4), ts(13, -17),
+        ts(29, -12), ts(-1, 17), ts(-20, 14), ts(-7, 17), ts(-8, 17), ts(-4, 38), ts(-38, 23), ts(-29, 11),
+        ts(-9, 10), ts(24, 17), ts(2, 23), ts(-16, 15), ts(-20, 20), ts(6, 45), ts(22, 44), ts(-22, 13),
+        ts(-17, -8), ts(-20, 22), ts(-12, 24), ts(-27, 27), ts(-30, 26), ts(-25, 33), ts(-14, 26), ts(-36, 3),
+        ts(-49, -18), ts(-1, -4), ts(-27, 21), ts(-39, 24), ts(-46, 27), ts(-44, 23), ts(-33, 9), ts(-51, -11),
+        ts(-14, -19), ts(-14, -3), ts(-22, 11), ts(-46, 21), ts(-44, 23), ts(-30, 16), ts(-15, 7), ts(-27, -9),
+        ts(1, -27), ts(7, -11), ts(-8, 4), ts(-64, 13), ts(-43, 14), ts(-16, 4), ts(9, -5), ts(8, -17),
+        ts(-15, -53), ts(36, -34), ts(12, -21), ts(-54, -11), ts(8, -28), ts(-28, -14), ts(24, -24), ts(14, -43),
+    ],
+    queen_pst: [
+        ts(-28, -9), ts(0, 22), ts(29, 22), ts(12, 27), ts(59, 27), ts(44, 19), ts(43, 10), ts(45, 20),
+        ts(-24, -17), ts(-39, 20), ts(-5, 32), ts(1, 41), ts(-16, 58), ts(57, 25), ts(28, 30), ts(54, 0),
+        ts(-13, -20), ts(-17, 6), ts(7, 9), ts(8, 49), ts(29, 47), ts(56, 35), ts(47, 19), ts(57, 9),
+        ts(-27, 3), ts(-27, 22), ts(-16, 24), ts(-16, 45), ts(-1, 57), ts(17, 40), ts(-2, 57), ts(1, 36),
+        ts(-9, -18), ts(-26, 28), ts(-9, 19), ts(-10, 47), ts(-2, 31), ts(-4, 34), ts(3, 39), ts(-3, 23),
+        ts(-14, -16), ts(2, -27), ts(-11, 15), ts(-2, 6), ts(-5, 9), ts(2, 17), ts(14, 10), ts(5, 5),
+        ts(-35, -22), ts(-8, -23), ts(11, -30), ts(2, -16), ts(8, -16), ts(15, -23), ts(-3, -36), ts(1, -32),
+        ts(-1, -33), ts(-18, -28), ts(-9, -22), ts(10, -43), ts(-15, -5), ts(-25, -32), ts(-31, -20), ts(-50, -41),
+    ],
+    knight_pst: [
+        ts(-167, -58), ts(-89, -38), ts(-34, -13), ts(-49, -28), ts(61, -31), ts(-97, -27), ts(-15, -63), ts(-107, -99),
+        ts(-73, -25), ts(-41, -8), ts(72, -25), ts(36, -2), ts(23, -9), ts(62, -25), ts(7, -24), ts(-17, -52),
+        ts(-47, -24), ts(60, -20), ts(37, 10), ts(65, 9), ts(84, -1), ts(129, -9), ts(73, -19), ts(44, -41),
+        ts(-9, -17), ts(17, 3), ts(19, 22), ts(53, 22), ts(37, 22), ts(69, 11), ts(18, 8), ts(22, -18),
+        ts(-13, -18), ts(4, -6), ts(16, 16), ts(13, 25), ts(28, 16), ts(19, 17), ts(21, 4), ts(-8, -18),
+        ts(-23, -23), ts(-9, -3), ts(12, -1), ts(10, 15), ts(19, 10), ts(17, -3), ts(25, -20), ts(-16, -22),
+        ts(-29, -42), ts(-53, -20), ts(-12, -10), ts(-3, -5), ts(-1, -2), ts(18, -20), ts(-14, -23), ts(-19, -44),
+        ts(-105, -29), ts(-21, -51), ts(-58, -23), ts(-33, -15), ts(-17, -22), ts(-28, -18), ts(-19, -50), ts(-23, -64),
+    ],
+    bishop_pst: [
+        ts(-29, -14), ts(4, -21), ts(-82, -11), ts(-37, -8), ts(-25, -7), ts(-42, -9), ts(7, -17), ts(-8, -24),
+        ts(-26, -8), ts(16, -4), ts(-18, 7), ts(-13, -12), ts(30, -3), ts(59, -13), ts(18, -4), ts(-47, -14),
+        ts(-16, 2), ts(37, -8), ts(43, 0), ts(40, -1), ts(35, -2), ts(50, 6), ts(37, 0), ts(-2, 4),
+        ts(-4, -3), ts(5, 9), ts(19, 12), ts(50, 9), ts(37, 14), ts(37, 10), ts(7, 3), ts(-2, 2),
+        ts(-6, -6), ts(13, 3), ts(13, 13), ts(26, 19), ts(34, 7), ts(12, 10), ts(10, -3), ts(4, -9),
+        ts(0, -12), ts(15, -3), ts(15, 8), ts(15, 10), ts(14, 13), ts(27, 3), ts(18, -7), ts(10, -15),
+        ts(4, -14), ts(15, -18), ts(16, -7), ts(0, -1), ts(7, 4), ts(21, -9), ts(33, -15), ts(1, -27),
+        ts(-33, -23), ts(-3, -9), ts(-14, -23), ts(-21, -5), ts(-13, -9), ts(-12, -16), ts(-39, -5), ts(-21, -17),
+    ],
+    rook_pst: [
+        ts(32, 13), ts(42, 10), ts(32, 18), ts(51, 15), ts(63, 12), ts(9, 12), ts(31, 8), ts(43, 5),
+        ts(27, 11), ts(32, 13), ts(58, 13), ts(62, 11), ts(80, -3), ts(67, 3), ts(26, 8), ts(44, 3),
+        ts(-5, 7), ts(19, 7), ts(26, 7), ts(36, 5), ts(17, 4), ts(45, -3), ts(61, -5), ts(16, -3),
+        ts(-24, 4), ts(-11, 3), ts(7, 13), ts(26, 1), ts(24, 2), ts(35, 1), ts(-8, -1), ts(-20, 2),
+        ts(-36, 3), ts(-26, 5), ts(-12, 8), ts(-1, 4), ts(9, -5), ts(-7, -6), ts(6, -8), ts(-23, -11),
+        ts(-45, -4), ts(-25, 0), ts(-16, -5), ts(-17, -1), ts(3, -7), ts(0, -12), ts(-5, -8), ts(-33, -16),
+        ts(-44, -6), ts(-16, -6), ts(-20, 0), ts(-9, 2), ts(-1, -9), ts(11, -9), ts(-6, -11), ts(-71, -3),
+        ts(-19, -9), ts(-13, 2), ts(1, 3), ts(17, -1), ts(16, -5), ts(7, -13), ts(-37, 4), ts(-26, -20),
+    ],
+    pawn_pst: [
+        ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0),
+        ts(98, 178), ts(134, 173), ts(61, 158), ts(95, 134), ts(68, 147), ts(126, 132), ts(34, 165), ts(-11, 187),
+        ts(-6, 94), ts(7, 100), ts(26, 85), ts(31, 67), ts(65, 56), ts(56, 53), ts(25, 82), ts(-20, 84),
+        ts(-14, 32), ts(13, 24), ts(6, 13), ts(21, 5), ts(23, -2), ts(12, 4), ts(17, 17), ts(-23, 17),
+        ts(-27, 13), ts(-2, 9), ts(-5, -3), ts(12, -7), ts(17, -7), ts(6, -8), ts(10, 3), ts(-25, -1),
+        ts(-26, 4), ts(-4, 7), ts(-4, -6), ts(-10, 1), ts(3, 0), ts(3, -5), ts(33, -1), ts(-12, -8),
+        ts(-35, 13), ts(-1, 8), ts(-20, 8), ts(-23, 10), ts(-15, 13), ts(24, 0), ts(38, 2), ts(-22, -7),
+        ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0), ts(0, 0),
+    ],
+    doubled_pawn: ts(-10, -25),
+    isolated_pawn: ts(-8, -12),
+    backward_pawn: ts(-6, -10),
+    connected_pawn: [ts(0, 0), ts(3, 1), ts(5, 2), ts(8, 5), ts(15, 12), ts(30, 25), ts(50, 45), ts(0, 0)],
+    passed_pawn: [ts(0, 0), ts(2, 8), ts(5, 12), ts(10, 20), ts(25, 40), ts(50, 80), ts(90, 140), ts(0, 0)],
+    passed_free: [ts(0, 0), ts(0, 2), ts(0, 4), ts(2, 8), ts(5, 16), ts(10, 30), ts(20, 50), ts(0, 0)],
+    passed_own_king: ts(0, -3),
+    passed_enemy_king: ts(0, 5),
+    passed_unstoppable: ts(0, 600),
+    knight_mobility: [
+        ts(-31, -40), ts(-26, -28), ts(-6, -15), ts(-2, -8), ts(1, 2), ts(6, 5), ts(11, 8),
+        ts(14, 10), ts(16, 12),
+    ],
+    bishop_mobility: [
+        ts(-24, -30), ts(-10, -12), ts(8, -2), ts(13, 6), ts(19, 12), ts(25, 21), ts(27, 27),
+        ts(31, 28), ts(31, 32), ts(34, 36), ts(40, 39), ts(40, 43), ts(45, 44), ts(49, 48),
+    ],
+    rook_mobility: [
+        ts(-30, -39), ts(-10, -8), ts(1, 11), ts(1, 19), ts(1, 35), ts(5, 49), ts(11, 51),
+        ts(15, 60), ts(20, 67), ts(20, 69), ts(20, 79), ts(24, 82), ts(28, 84), ts(28, 84),
+        ts(31, 86),
+    ],
+    queen_mobility: [
+        ts(-15, -24), ts(-6, -15), ts(-4, -4), ts(-5, 10), ts(10, 20), ts(12, 28), ts(12, 30),
+        ts(18, 38), ts(19, 39), ts(27, 48), ts(32, 48), ts(33, 50), ts(33, 61), ts(33, 64),
+        ts(34, 66), ts(34, 67), ts(36, 68), ts(36, 71), ts(39, 74), ts(40, 75), ts(47, 76),
+        ts(54, 84), ts(54, 84), ts(54, 86), ts(55, 91), ts(57, 91), ts(57, 96), ts(58, 110),
+    ],
+    rook_open_file: ts(25, 10),
+    rook_semi_open_file: ts(10, 5),
+    rook_on_seventh: ts(10, 20),
+    bishop_pair: ts(30, 50),
+    knight_outpost: ts(20, 10),
+    trapped_rook: ts(-40, -5),
+    trapped_bishop: ts(-50, -50),
+    king_zone_attack: [ts(0, 0), ts(10, 2), ts(6, 1), ts(5, 1), ts(7, 1), ts(0, 0)],
+    king_safe_check: [ts(0, 0), ts(25, 5), ts(30, 5), ts(20, 5), ts(30, 5), ts(0, 0)],
+    pawn_shield: [ts(15, 0), ts(8, 0)],
+    pawn_storm: [
+        ts(0, 0), ts(0, 0), ts(-25, -5), ts(-15, 0), ts(-8, 0), ts(0, 0), ts(0, 0), ts(0, 0),
+    ],
+    king_open_file: ts(-25, 0),
+    king_semi_open_file: ts(-12, 0),
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+// the number of TaperedScore weights in EvalParams, which has no other fields
+pub const PARAM_COUNT: usize = size_of::<EvalParams>() / size_of::<TaperedScore>();
+// material and piece-square weights, the first ones of EvalParams
+pub const PIECE_SQUARE_PARAMS: usize = 6 + 6 * 64;
+
+/* EvalParams as a flat parameter vector, for the tuner. every weight is two entries of the
+vector, its mg value followed by its eg value, in the order of the fields.
+
+note: fields of a single weight are slices of length 1, all arrays are longer than that. */
+impl EvalParams {
+    pub fn fields_mut(&mut self) -> [(&'static str, &mut [TaperedScore]); 33] {
+        [
+            ("material", &mut self.material),
+            ("king_pst", &mut self.king_pst),
+            ("queen_pst", &mut self.queen_pst),
+            ("knight_pst", &mut self.knight_pst),
+            ("bishop_pst", &mut self.bishop_pst),
+            ("rook_pst", &mut self.rook_pst),
+            ("pawn_pst", &mut self.pawn_pst),
+            ("doubled_pawn", std::slice::from_mut(&mut self.doubled_pawn)),
+            ("isolated_pawn", std::slice::from_mut(&mut self.isolated_pawn)),
+            ("backward_pawn", std::slice::from_mut(&mut self.backward_pawn)),
+            ("connected_pawn", &mut self.connected_pawn),
+            ("passed_pawn", &mut self.passed_pawn),
+            ("passed_free", &mut self.passed_free),
+            ("passed_own_king", std::slice::from_mut(&mut self.passed_own_king)),
+            ("passed_enemy_king", std::slice::from_mut(&mut self.passed_enemy_king)),
+            ("passed_unstoppable", std::slice::from_mut(&mut self.passed_unstoppable)),
+            ("knight_mobility", &mut self.knight_mobility),
+            ("bishop_mobility", &mut self.bishop_mobility),
+            ("rook_mobility", &mut self.rook_mobility),
+            ("queen_mobility", &mut self.queen_mobility),
+            ("rook_open_file", std::slice::from_mut(&mut self.rook_open_file)),
+            ("rook_semi_open_file", std::slice::from_mut(&mut self.rook_semi_open_file)),
+            ("rook_on_seventh", std::slice::from_mut(&mut self.rook_on_seventh)),
+            ("bishop_pair", std::slice::from_mut(&mut self.bishop_pair)),
+            ("knight_outpost", std::slice::from_mut(&mut self.knight_outpost)),
+            ("trapped_rook", std::slice::from_mut(&mut self.trapped_rook)),
+            ("trapped_bishop", std::slice::from_mut(&mut self.trapped_bishop)),
+            ("king_zone_attack", &mut self.king_zone_attack),
+            ("king_safe_check", &mut self.king_safe_check),
+            ("pawn_shield", &mut self.pawn_shield),
+            ("pawn_storm", &mut self.pawn_storm),
+            ("king_open_file", std::slice::from_mut(&mut self.king_open_file)),
+            ("king_semi_open_file", std::slice::from_mut(&mut self.king_semi_open_file)),
+        ]
+    }
+
+    pub fn to_vec(mut self) -> Vec<i32> {
+        let mut values = Vec::with_capacity(2 * PARAM_COUNT);
+        for (_, weights) in self.fields_mut() {
+            for weight in weights.iter() {
+                values.push(weight.mg);
+                values.push(weight.eg);
+            }
+        }
+        assert!(values.len() == 2 * PARAM_COUNT, "to_vec error: fields missing from fields_mut!");
+        values
+    }
+
+    pub fn from_vec(values: &[i32]) -> EvalParams {
+        assert!(values.len() == 2 * PARAM_COUNT, "from_vec error: wrong number of values!");
+        let mut params = DEFAULT_PARAMS;
+        let mut i: usize = 0;
+        for (_, weights) in params.fields_mut() {
+            for weight in weights.iter_mut() {
+                *weight = TaperedScore::new(values[i], values[i + 1]);
+                i += 2;
+            }
+        }
+        params
+    }
+
+    // the params as rust source, in the layout of DEFAULT_PARAMS
+    pub fn to_rust(mut self, name: &str) -> String {
+        let mut out = String::new();
+        out.push_str("#[rustfmt::skip]\n");
+        out.push_str(&format!("pub const {}: EvalParams = EvalParams {{\n", name));
+        for (field, weights) in self.fields_mut() {
+            let values: Vec<String> =
+                weights.iter().map(|x| format!("ts({}, {})", x.mg, x.eg)).collect();
+            if values.len() == 1 {
+                out.push_str(&format!("    {}: {},\n", field, values[0]));
+            } else if values.len() <= 8 {
+                out.push_str(&format!("    {}: [{}],\n", field, values.join(", ")));
+            } else {
+                // piece-square tables by rank, as the board is seen from white's side
+                let width = if values.len() == 64 { 8 } else { 7 };
+                out.push_str(&format!("    {}: [\n", field));
+                for line in values.chunks(width) {
+                    out.push_str(&format!("        {},\n", line.join(", ")));
+                }
+                out.push_str("    ],\n");
+            }
+        }
+        out.push_str("};\n");
+        out
+    }
+
+    // the piece-square table of K,Q,N,B,R,P
+    pub const fn pst(&self, piece: usize) -> &[TaperedScore; 64] {
+        match piece {
+            0 => &self.king_pst,
+            1 => &self.queen_pst,
+            2 => &self.knight_pst,
+            3 => &self.bishop_pst,
+            4 => &self.rook_pst,
+            _ => &self.pawn_pst,
+        }
+    }
+
+    // material plus table value of a white piece on square, black pieces use the square
+    // mirrored to white's side (square ^ 56)
+    pub const fn piece_square(&self, piece: usize, square: usize) -> TaperedScore {
+        let material = self.material[piece];
+        let value = self.pst(piece)[63 - square];
+        TaperedScore::new(material.mg + value.mg, material.eg + value.eg)
+    }
+
+    // whether the material and piece-square weights are those of other
+    pub fn same_piece_squares(&self, other: &EvalParams) -> bool {
+        self.material == other.material && (0..6).all(|i| self.pst(i) == other.pst(i))
+    }
+}
+
+// phase weights of K,Q,N,B,R,P
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 4, 1, 1, 2, 0];
+pub const MAX_PHASE: i32 = 24;
+
+// material plus table value of each of the 12 pieces on each square, negative for black
+pub const PST: [[TaperedScore; 64]; 12] = generate_pst(&DEFAULT_PARAMS);
+
+const fn generate_pst(params: &EvalParams) -> [[TaperedScore; 64]; 12] {
+    let mut pst = [[TaperedScore::ZERO; 64]; 12];
+    let mut i: usize = 0;
+    while i < 6 {
+        let mut square: usize = 0;
+        while square < 64 {
+            // white's value, then black's: mirrored to white's side, negated
+            pst[i][square] = params.piece_square(i, square);
+            let value = params.piece_square(i, square ^ 56);
+            pst[i + 6][square] = TaperedScore::new(-value.mg, -value.eg);
+            square += 1;
+        }
+        i += 1;
+    }
+    pst
+}
+
+pub const fn get_piece_phase(piece: (Side, PieceType)) -> i32 {
+    PHASE_WEIGHTS[cpt_index(piece) % 6]
+}
+
+pub const fn get_piece_pst(square: usize, piece: (Side, PieceType)) -> TaperedScore {
+    PST[cpt_index(piece)][square]
+}
+
+impl ChessBoard {
+    // piece-square total and phase recomputed from scratch, see update_state for the
+    // incremental version
+    pub const fn compute_pst(&self) -> (TaperedScore, i32) {
+        let mut score = TaperedScore::ZERO;
+        let mut phase: i32 = 0;
+        let mut square: usize = 0;
+        while square < 64 {
+            if let Some(piece) = self.mailbox[square] {
+                let value = get_piece_pst(square, piece);
+                score.mg += value.mg;
+                score.eg += value.eg;
+                phase += get_piece_phase(piece);
+            }
+            square += 1;
+        }
+        (score, phase)
+    }
+
+    // incremental evaluation state: piece-square total, phase and pawn hash
+    // note: used by update_state when a piece is put on or taken off a square
+    pub const fn add_piece_eval(&mut self, square: usize, piece: (Side, PieceType)) {
+        let value = get_piece_pst(square, piece);
+        self.pst_score.mg += value.mg;
+        self.pst_score.eg += value.eg;
+        self.phase += get_piece_phase(piece);
+        if let PieceType::Pawn = piece.1 {
+            self.pawn_hash ^= ZH::get_piece_hash(square, piece);
+        }
+    }
+
+    pub const fn remove_piece_eval(&mut self, square: usize, piece: (Side, PieceType)) {
+        let value = get_piece_pst(square, piece);
+        self.pst_score.mg -= value.mg;
+        self.pst_score.eg -= value.eg;
+        self.phase -= get_piece_phase(piece);
+        if let PieceType::Pawn = piece.1 {
+            self.pawn_hash ^= ZH::get_piece_hash(square, piece);
+        }
+    }
+
+    // piece-square total of params recomputed from scratch, pst_score is the one of
+    // DEFAULT_PARAMS
+    pub fn compute_pst_with(&self, params: &EvalParams) -> TaperedScore {
+        let mut score = TaperedScore::ZERO;
+        let mut i: usize = 0;
+        while i < 6 {
+            let mut pieces = self.piece_bbs[i];
+            while let Some(square) = pieces.lsb_index() {
+                pieces = pieces.pop_bit(square);
+                score += params.piece_square(i, square);
+            }
+            let mut pieces = self.piece_bbs[i + 6];
+            while let Some(square) = pieces.lsb_index() {
+                pieces = pieces.pop_bit(square);
+                score -= params.piece_square(i, square ^ 56);
+            }
+            i += 1;
+        }
+        score
+    }
+
+    // static evaluation in centipawns from white's point of view
+    pub const fn tapered_eval(&self) -> i32 {
+        self.pst_score.taper(self.phase)
+    }
+}
+
+/* the evaluation used by the search, from the side to move's point of view.
+
+note: every search thread owns its own evaluator, so it may keep state (caches,
+      accumulators) without locking. evaluators are kept from one search to the next, and
+      cleared on a new game. on_make and on_unmake are called around every move the search
+      makes, with chess_move None for a null move, for evaluators that update their state
+      incrementally. */
+pub trait Evaluator: Clone + Send {
+    fn evaluate(&mut self, chessboard: &ChessBoard) -> Score;
+
+    fn on_make(
+        &mut self,
+        _before: &ChessBoard,
+        _chess_move: Option<ChessMove>,
+        _after: &ChessBoard,
+    ) {
+    }
+
+    fn on_unmake(&mut self) {}
+
+    // forgets everything cached, for a new game
+    fn clear(&mut self) {}
+}
+
+// white's point of view to the side to move's, and back
+pub const fn side_relative(chessboard: &ChessBoard, value: i32) -> Score {
+    match chessboard.side_to_move {
+        Side::White => Score::new(value),
+        Side::Black => Score::new(-value),
+    }
+}
+
+// the default evaluation: material, piece-square tables, pawn structure, pieces and king
+// safety
+// note: pst_score of the chessboard is only used if params has the material and piece-square
+//       weights of DEFAULT_PARAMS, otherwise they are recomputed at every evaluation
+#[derive(Debug, Clone)]
+pub struct HandCraftedEval {
+    pub params: EvalParams,
+    pub pawn_table: PawnTable,
+    // set by new, whether pst_score can be used
+    incremental_pst: bool,
+}
+
+impl HandCraftedEval {
+    pub fn new(params: EvalParams) -> HandCraftedEval {
+        HandCraftedEval {
+            params,
+            pawn_table: PawnTable::default(),
+            incremental_pst: params.same_piece_squares(&DEFAULT_PARAMS),
+        }
+    }
+}
+
+impl Default for HandCraftedEval {
+    fn default() -> Self {
+        HandCraftedEval::new(DEFAULT_PARAMS)
+    }
+}
+
+impl Evaluator for HandCraftedEval {
+    fn evaluate(&mut self, chessboard: &ChessBoard) -> Score {
+        let mut score = match self.incremental_pst {
+            true => chessboard.pst_score,
+            false => chessboard.compute_pst_with(&self.params),
+        };
+        score += evaluate_pawns(chessboard, &self.params, &mut self.pawn_table);
+        score += evaluate_pieces(chessboard, &self.params);
+        score += evaluate_king_safety(chessboard, &self.params);
+        side_relative(chessboard, endgame_value(chessboard, score))
+    }
+
+    fn clear(&mut self) {
+        self.pawn_table.clear();
+    }
+}
+
+// material only, tapered, for testing what the rest of the evaluation is worth
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct MaterialEval;
+
+impl Evaluator for MaterialEval {
+    fn evaluate(&mut self, chessboard: &ChessBoard) -> Score {
+        let mut score = TaperedScore::ZERO;
+        let mut i: usize = 0;
+        while i < 6 {
+            let count = chessboard.piece_bbs[i].data.count_ones() as i32
+                - chessboard.piece_bbs[i + 6].data.count_ones() as i32;
+            score += DEFAULT_PARAMS.material[i] * count;
+            i += 1;
+        }
+        side_relative(chessboard, score.taper(chessboard.phase))
+    }
+}
+
+pub const RANDOM_EVAL_RANGE: i32 = 100;
+
+// uniformly random scores in [-RANDOM_EVAL_RANGE, RANDOM_EVAL_RANGE], a baseline to test
+// against. seeded, so that searches with it are reproducible.
+#[derive(Debug, Clone)]
+pub struct RandomEval {
+    pub rng: StdRng,
+}
+
+impl RandomEval {
+    pub fn new(seed: u64) -> RandomEval {
+        RandomEval { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Default for RandomEval {
+    fn default() -> Self {
+        RandomEval::new(0)
+    }
+}
+
+impl Evaluator for RandomEval {
+    fn evaluate(&mut self, _chessboard: &ChessBoard) -> Score {
+        Score::new(self.rng.gen_range(-RANDOM_EVAL_RANGE..=RANDOM_EVAL_RANGE))
+    }
+}
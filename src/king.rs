@@ -0,0 +1,155 @@
+#![allow(dead_code)]
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::pawns::*;
+use crate::pieces::*;
+
+/* king safety, from attack maps around the king.
+
+the danger from the enemy pieces is the sum of their attacks on the king zone (the king
+square and the squares next to it) and their safe checks, scaled by the number of pieces
+taking part in the attack and by the non-pawn material the attacker has left: a lone
+piece or an attacker without a queen is not much of a threat.
+
+the pawn cover is scored separately: the pawn shield in front of the king, enemy pawns
+storming the files around it, and open files next to the king. */
+
+// the attack counts fully from this many attacking pieces on
+pub const KING_ATTACKERS_FULL: i32 = 4;
+// non-pawn phase weight of a full set of pieces of one side
+pub const SIDE_PHASE: i32 = MAX_PHASE / 2;
+
+// squares attacked by a piece of piece_type on square
+const fn piece_attacks(piece_type: PieceType, square: usize, occupied: BB) -> BB {
+    match piece_type {
+        PieceType::Knight => KNIGHT_ATTACKS[square],
+        PieceType::Bishop => get_bishop_attack(square, occupied),
+        PieceType::Rook => get_rook_attack(square, occupied),
+        PieceType::Queen => get_queen_attack(square, occupied),
+        PieceType::King => KING_ATTACKS[square],
+        PieceType::Pawn => BB::ZERO,
+    }
+}
+
+// squares attacked by any of side's pieces
+pub fn attack_map(chessboard: &ChessBoard, side: Side) -> BB {
+    let o = 6 * side as usize;
+    let occupied = chessboard.blockers();
+    let mut data = pawn_attacks(chessboard, side).data;
+    // K,Q,N,B,R
+    let piece_types =
+        [PieceType::King, PieceType::Queen, PieceType::Knight, PieceType::Bishop, PieceType::Rook];
+    for (i, piece_type) in piece_types.into_iter().enumerate() {
+        let mut pieces = chessboard.piece_bbs[o + i];
+        while let Some(square) = pieces.lsb_index() {
+            pieces = pieces.pop_bit(square);
+            data |= piece_attacks(piece_type, square, occupied).data;
+        }
+    }
+    BB { data }
+}
+
+pub const fn king_square(chessboard: &ChessBoard, side: Side) -> usize {
+    match chessboard.piece_bbs[6 * side as usize].lsb_index() {
+        Some(x) => x,
+        None => panic!("king_square error: king not found!"),
+    }
+}
+
+// danger to side's king from the enemy pieces, from side's point of view (so negative)
+pub fn king_attack(chessboard: &ChessBoard, side: Side, params: &EvalParams) -> TaperedScore {
+    let enemy = side.update();
+    let e = 6 * enemy as usize;
+    let king = king_square(chessboard, side);
+    let occupied = chessboard.blockers();
+    let zone = KING_ATTACKS[king].data | (1u64 << king);
+    // checks from squares the defender does not cover and the attacker can move to
+    let safe = !attack_map(chessboard, side).data & !side_pieces(chessboard, enemy).data;
+
+    let mut danger = TaperedScore::ZERO;
+    let mut attackers: i32 = 0;
+    // Q,N,B,R of the attacker
+    let piece_types = [PieceType::Queen, PieceType::Knight, PieceType::Bishop, PieceType::Rook];
+    for (i, piece_type) in piece_types.into_iter().enumerate() {
+        let checks = piece_attacks(piece_type, king, occupied).data;
+        let mut pieces = chessboard.piece_bbs[e + i + 1];
+        while let Some(square) = pieces.lsb_index() {
+            pieces = pieces.pop_bit(square);
+            let attacks = piece_attacks(piece_type, square, occupied).data;
+            let zone_attacks = (attacks & zone).count_ones() as i32;
+            if zone_attacks > 0 {
+                attackers += 1;
+                danger += params.king_zone_attack[i + 1] * zone_attacks;
+            }
+            let safe_checks = (attacks & checks & safe).count_ones() as i32;
+            danger += params.king_safe_check[i + 1] * safe_checks;
+        }
+    }
+
+    let mut material: i32 = 0;
+    let mut i: usize = 1;
+    while i < 5 {
+        material += PHASE_WEIGHTS[i] * chessboard.piece_bbs[e + i].data.count_ones() as i32;
+        i += 1;
+    }
+    let material = material.min(SIDE_PHASE);
+    let scale = attackers.min(KING_ATTACKERS_FULL) * material;
+    let danger = TaperedScore::new(
+        danger.mg * scale / (KING_ATTACKERS_FULL * SIDE_PHASE),
+        danger.eg * scale / (KING_ATTACKERS_FULL * SIDE_PHASE),
+    );
+    -danger
+}
+
+// pawn shield, pawn storm and open files around side's king, from side's point of view
+pub fn king_shelter(chessboard: &ChessBoard, side: Side, params: &EvalParams) -> TaperedScore {
+    let s = side as usize;
+    let own_pawns = chessboard.piece_bbs[5 + 6 * s].data;
+    let enemy_pawns = chessboard.piece_bbs[11 - 6 * s].data;
+    let king = king_square(chessboard, side);
+    let king_rank = relative_rank(side, king);
+
+    let mut score = TaperedScore::ZERO;
+    let col = COLS[king];
+    let first = col.saturating_sub(1);
+    let last = (col + 1).min(7);
+    let mut file = first;
+    while file <= last {
+        let mask = FILE_MASKS[file].data;
+        if (own_pawns | enemy_pawns) & mask == 0 {
+            score += params.king_open_file;
+        } else if own_pawns & mask == 0 {
+            score += params.king_semi_open_file;
+        }
+
+        // own pawns directly in front of the king
+        let front = FRONT_SPANS[s][king - COLS[king] + file].data;
+        let mut shield = BB { data: own_pawns & mask & front };
+        while let Some(square) = shield.lsb_index() {
+            shield = shield.pop_bit(square);
+            let ahead = relative_rank(side, square) - king_rank;
+            if ahead <= 2 {
+                score += params.pawn_shield[ahead - 1];
+            }
+        }
+
+        let mut storm = BB { data: enemy_pawns & mask };
+        while let Some(square) = storm.lsb_index() {
+            storm = storm.pop_bit(square);
+            score += params.pawn_storm[relative_rank(side, square)];
+        }
+        file += 1;
+    }
+    score
+}
+
+pub fn king_safety(chessboard: &ChessBoard, side: Side, params: &EvalParams) -> TaperedScore {
+    king_attack(chessboard, side, params) + king_shelter(chessboard, side, params)
+}
+
+// king safety from white's point of view
+pub fn evaluate_king_safety(chessboard: &ChessBoard, params: &EvalParams) -> TaperedScore {
+    king_safety(chessboard, Side::White, params) - king_safety(chessboard, Side::Black, params)
+}
@@ -1,23 +1,52 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
+mod bench;
 mod bitboard;
 mod chessboard;
 mod chessmove;
+mod datagen;
+mod endgame;
+mod eval;
+mod king;
+mod nnets;
+mod pawns;
+mod pieces;
+mod score;
+mod search;
+mod stats;
+mod trace;
+mod ttable;
+mod tune;
 use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
 use std::time::Instant;
 
+use crate::bench::*;
 use crate::bitboard::*;
 use crate::chessboard::*;
 use crate::chessmove::*;
+use crate::datagen::*;
+use crate::eval::*;
+use crate::nnets::*;
+use crate::search::*;
+use crate::stats::*;
+use crate::ttable::*;
+use crate::tune::*;
 use rand::Rng;
 
 /* crappy global variables */
 
 pub const DEBUG: bool = false;
+// every ply of search keeps a chessboard on the stack
+pub const SEARCH_STACK_SIZE: usize = 64 << 20;
 
 /* custom position for webperft */
 #[rustfmt::skip]
@@ -121,6 +150,42 @@ pub const POS0: [BB; 12] = [
 ];
 
 fn main() -> io::Result<()> {
+    // "stowockfish bench [depth]" runs the bench and exits
+    // "stowockfish bench pruning [depth]" runs it with each pruning option off in turn
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|x| x.as_str()) == Some("bench") {
+        match args.get(2).map(|x| x.as_str()) {
+            Some("pruning") => {
+                pruning_bench(parse_bench_depth(args.get(3).map(|x| x.as_str())));
+            }
+            x => {
+                bench(parse_bench_depth(x));
+            }
+        }
+        return Ok(());
+    }
+    // "stowockfish tune <positions file> [iterations] [output]" tunes the eval and exits
+    if args.get(1).map(|x| x.as_str()) == Some("tune") {
+        let path = match args.get(2) {
+            Some(x) => x,
+            None => {
+                println!("usage: stowockfish tune <positions file> [iterations] [output]");
+                return Ok(());
+            }
+        };
+        let iterations = match args.get(3).map(|x| x.parse::<usize>()) {
+            Some(Ok(x)) => x,
+            _ => TUNE_ITERATIONS,
+        };
+        let output = args.get(4).map(|x| x.as_str()).unwrap_or(TUNE_OUTPUT);
+        return tune(path, iterations, output);
+    }
+    // "stowockfish datagen [games n] [threads n] [seed n] [nodes n] [output file]" plays
+    // self-play games for training data and exits, see src/datagen.rs
+    if args.get(1).map(|x| x.as_str()) == Some("datagen") {
+        let args: Vec<&str> = args[2..].iter().map(|x| x.as_str()).collect();
+        return datagen(&DatagenConfig::from_args(&args)).map(|_| ());
+    }
     let mut chessboard = ChessBoard::default();
     uci_loop(&mut chessboard)
 }
@@ -128,15 +193,62 @@ fn main() -> io::Result<()> {
 const TEST_FEN: &str = "rnbqkb1r/pp1p1pPp/8/2p1pP2/1P1P4/3P3P/P1P1P3/RNBQKBNR w KQkq e6 0 1";
 const TEST_FEN2: &str = "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1";
 
+// "setoption name <name> value <value>", both name and value may contain spaces
+fn parse_uci_setoption(cmds: &[&str]) -> (String, String) {
+    let mut name: Vec<&str> = Vec::new();
+    let mut value: Vec<&str> = Vec::new();
+    let mut reading_value = false;
+    for cmd in cmds {
+        match *cmd {
+            "name" if !reading_value && name.is_empty() => {}
+            "value" if !reading_value => reading_value = true,
+            _ if reading_value => value.push(cmd),
+            _ => name.push(cmd),
+        }
+    }
+    (name.join(" "), value.join(" "))
+}
+
+// the depth argument of "bench", BENCH_DEPTH if missing or malformed
+fn parse_bench_depth(arg: Option<&str>) -> usize {
+    match arg.map(|x| x.parse::<usize>()) {
+        Some(Ok(x)) if x > 0 => x,
+        _ => BENCH_DEPTH,
+    }
+}
+
+// signals the running search to stop and waits for it to print its bestmove
+fn stop_search(search_thread: &mut Option<JoinHandle<()>>, signals: &SearchSignals) {
+    if let Some(handle) = search_thread.take() {
+        signals.stop.store(true, Ordering::SeqCst);
+        _ = handle.join();
+    }
+}
+
 pub fn uci_loop(chessboard: &mut ChessBoard) -> io::Result<()> {
     let mut reader = BufReader::new(io::stdin());
     let mut buffer = String::with_capacity(1 << 11);
+    let signals = Arc::new(SearchSignals::default());
+    let mut search_thread: Option<JoinHandle<()>> = None;
+    let mut config = SearchConfig::default();
+    // note: shared with the search threads, which access it without locking
+    let mut tt = Arc::new(TranspositionTable::new(DEFAULT_HASH_MB));
+    // statistics of the last finished search, for the "stats" command
+    let last_stats = Arc::new(Mutex::new(SearchStats::default()));
+    // the net of EvalFile, searched with when UseNNUE is on
+    let mut network = Arc::new(Network::embedded());
+    let mut use_nnue = false;
+    // one evaluator per search thread, kept between searches like the tt so that their
+    // caches are not thrown away with every "go"
+    let evaluators = Arc::new(Mutex::new(vec![HandCraftedEval::default()]));
+    let nnue_evaluators = Arc::new(Mutex::new(vec![NnueEval::new(Arc::clone(&network))]));
     while let Ok(count) = reader.read_line(&mut buffer) {
         if DEBUG {
             print!("buffer:{}", buffer);
         }
 
         if count == 0 {
+            stop_search(&mut search_thread, &signals);
             return Ok(());
         }
 
@@ -146,16 +258,121 @@ pub fn uci_loop(chessboard: &mut ChessBoard) -> io::Result<()> {
             Some("position") => {
                 chessboard.parse_uci_position_cmd(cmds.collect::<Vec<&str>>().join(" ").as_str())
             }
-            Some("ucinewgame") => _ = chessboard.parse_uci_go_cmd("startpos"),
-            Some("go") => println!(
-                "{}",
-                chessboard.parse_uci_go_cmd(cmds.collect::<Vec<&str>>().join(" ").as_str())
-            ),
-            Some("quit") => return Ok(()),
+            Some("ucinewgame") => {
+                stop_search(&mut search_thread, &signals);
+                *chessboard = ChessBoard::default();
+                tt.clear();
+                evaluators.lock().unwrap().iter_mut().for_each(|x| x.clear());
+                nnue_evaluators.lock().unwrap().iter_mut().for_each(|x| x.clear());
+            }
+            Some("go") => {
+                stop_search(&mut search_thread, &signals);
+                let cmd_str = cmds.collect::<Vec<&str>>().join(" ");
+                let limits = SearchLimits::from_uci(cmd_str.as_str(), chessboard);
+                signals.reset(limits.ponder);
+                let chessboard = chessboard.clone();
+                let signals = Arc::clone(&signals);
+                let config = config;
+                let tt = Arc::clone(&tt);
+                let last_stats = Arc::clone(&last_stats);
+                let evaluators = Arc::clone(&evaluators);
+                let nnue_evaluators = Arc::clone(&nnue_evaluators);
+                let handle =
+                    thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn(move || {
+                        let result = match use_nnue {
+                            true => chessboard.search_with_evaluators(
+                                &limits,
+                                &config,
+                                &tt,
+                                &mut nnue_evaluators.lock().unwrap(),
+                                signals,
+                            ),
+                            false => chessboard.search_with_evaluators(
+                                &limits,
+                                &config,
+                                &tt,
+                                &mut evaluators.lock().unwrap(),
+                                signals,
+                            ),
+                        };
+                        println!("{}", result.to_uci());
+                        *last_stats.lock().unwrap() = result.stats;
+                    });
+                search_thread = Some(handle?);
+            }
+            Some("stop") => stop_search(&mut search_thread, &signals),
+            Some("setoption") => {
+                let (name, value) = parse_uci_setoption(cmds.collect::<Vec<&str>>().as_slice());
+                if name.eq_ignore_ascii_case("hash") {
+                    stop_search(&mut search_thread, &signals);
+                    match value.parse::<usize>() {
+                        Ok(x) => tt = Arc::new(TranspositionTable::new(x)),
+                        Err(_) => println!("info string invalid hash size: {}", value),
+                    }
+                } else if name.eq_ignore_ascii_case("evalfile") {
+                    stop_search(&mut search_thread, &signals);
+                    let loaded = match value.as_str() {
+                        "" | EMBEDDED_NET => Ok(Network::embedded()),
+                        path => Network::load(path),
+                    };
+                    match loaded {
+                        Ok(x) => {
+                            network = Arc::new(x);
+                            *nnue_evaluators.lock().unwrap() =
+                                vec![NnueEval::new(Arc::clone(&network))];
+                        }
+                        Err(e) => println!("info string could not load {}: {}", value, e),
+                    }
+                } else if name.eq_ignore_ascii_case("usennue") {
+                    match value.parse::<bool>() {
+                        Ok(x) => use_nnue = x,
+                        Err(_) => println!("info string invalid value: {} {}", name, value),
+                    }
+                } else if !config.set_option(name.as_str(), value.as_str()) {
+                    println!("info string unknown option or value: {} {}", name, value);
+                }
+            }
+            Some("ponderhit") => signals.ponder.store(false, Ordering::SeqCst),
+            Some("debug") => config.debug = cmds.next() == Some("on"),
+            // non-standard: statistics of the last search
+            Some("stats") => println!("{}", last_stats.lock().unwrap()),
+            // non-standard: evaluation breakdown of the current position
+            Some("eval") => {
+                println!("{}", chessboard.eval_trace());
+                if use_nnue {
+                    let score = NnueEval::new(Arc::clone(&network)).evaluate(chessboard);
+                    let score = side_relative(chessboard, score.data).data;
+                    println!("NNUE evaluation: {:+.2} (white side)", score as f64 / 100.0);
+                }
+            }
+            // non-standard: "bench [depth]" and "bench pruning [depth]", see src/bench.rs
+            Some("bench") => {
+                stop_search(&mut search_thread, &signals);
+                match cmds.next() {
+                    Some("pruning") => {
+                        pruning_bench(parse_bench_depth(cmds.next()));
+                    }
+                    x => {
+                        bench(parse_bench_depth(x));
+                    }
+                }
+            }
+            Some("quit") => {
+                stop_search(&mut search_thread, &signals);
+                return Ok(());
+            }
             Some("uci") => {
                 // print engine info
                 println!("id name Stowockfish/PawnPusher");
                 println!("id name Fangs");
+                println!("option name Ponder type check default false");
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                );
+                println!("option name UseNNUE type check default false");
+                println!("option name EvalFile type string default {}", EMBEDDED_NET);
+                println!("{}", config.uci_options());
                 println!("uciok");
             }
             _ => {} //???
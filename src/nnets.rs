@@ -1 +1,568 @@
+#![allow(dead_code)]
 
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::chessmove::*;
+use crate::eval::*;
+use crate::king::*;
+use crate::score::*;
+
+/* NNUE evaluation: a HalfKA feature transformer into two accumulators, one per perspective,
+followed by a clipped relu and a single output neuron.
+
+features: a feature is a (king square, piece, square) triple as seen from one side, with
+the board flipped vertically and the colours swapped for black, so that a perspective
+always sees its own pieces as white ones moving up the board. index of a feature:
+    64 * 12 * king + 64 * piece + square
+with piece as in cpt_index (K,Q,N,B,R,P own, then k,q,n,b,r,p enemy) and squares as in the
+rest of the engine (h1 = 0, a8 = 63). kings are features too (HalfKA, not HalfKP).
+
+the accumulators are updated incrementally in on_make from the difference of the piece
+bitboards, and popped in on_unmake. the accumulator of a perspective whose king moved is
+recomputed from scratch, since every one of its features depends on the king square.
+
+network file format, all little endian:
+    magic           4 bytes, "SWNN"
+    version         u32, NNUE_VERSION
+    feature set     u32, FEATURE_SET_HALFKA
+    hidden          u32, accumulator size of one perspective, a multiple of 32
+    scale           i32, see below
+    ft_biases       i16 * hidden
+    ft_weights      i16 * hidden * NNUE_FEATURES, all the weights of feature 0 first
+    out_weights     i8 * 2 * hidden, the side to move's perspective first
+    out_bias        i32
+and nothing after. the eval in centipawns, from the side to move's point of view, is
+    (sum of clamp(acc, 0, QA) * out_weight + out_bias) * scale / (QA * QB)
+
+note: the embedded net is not a trained one. it is the piece-square tables of eval.rs (the
+      average of mg and eg, as there is no phase to taper with) written as a net of this
+      format, so that NNUE plays sensibly without a net file and nets can be checked against
+      a known eval. a trained net is loaded with the EvalFile option. */
+
+pub const NNUE_MAGIC: [u8; 4] = *b"SWNN";
+pub const NNUE_VERSION: u32 = 1;
+pub const FEATURE_SET_HALFKA: u32 = 1;
+pub const NNUE_FEATURES: usize = 64 * 12 * 64;
+pub const MAX_HIDDEN: usize = 4096;
+// accumulators are clipped to 0..=QA, output weights are scaled by QB
+pub const QA: i32 = 255;
+pub const QB: i32 = 64;
+// value of EvalFile for the embedded net
+pub const EMBEDDED_NET: &str = "<empty>";
+
+// hidden size of the embedded net, and centipawns per unit of its accumulators
+const EMBEDDED_HIDDEN: usize = 64;
+const EMBEDDED_UNIT: i32 = 8;
+// accumulator bias of the embedded net, so that negative table sums are not clipped away
+const EMBEDDED_BIAS: i16 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    pub hidden: usize,
+    pub scale: i32,
+    pub ft_biases: Vec<i16>,
+    pub ft_weights: Vec<i16>,
+    pub out_weights: Vec<i8>,
+    pub out_bias: i32,
+}
+
+// index of the feature of piece on square, seen from perspective with its king on king
+pub const fn feature_index(perspective: Side, king: usize, piece: usize, square: usize) -> usize {
+    let (king, piece, square) = match perspective {
+        Side::White => (king, piece, square),
+        Side::Black => (king ^ 56, (piece + 6) % 12, square ^ 56),
+    };
+    64 * 12 * king + 64 * piece + square
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("network error: {}", message))
+}
+
+// reads little endian values off the front of a byte slice
+struct NetReader<'a> {
+    data: &'a [u8],
+}
+
+impl NetReader<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        if self.data.len() < n {
+            return Err(invalid_data("file too short"));
+        }
+        let (x, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(x)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let x = self.take(4)?;
+        Ok(u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn i16s(&mut self, n: usize) -> io::Result<Vec<i16>> {
+        let x = self.take(2 * n)?;
+        Ok(x.chunks_exact(2).map(|y| i16::from_le_bytes([y[0], y[1]])).collect())
+    }
+
+    fn i8s(&mut self, n: usize) -> io::Result<Vec<i8>> {
+        Ok(self.take(n)?.iter().map(|x| *x as i8).collect())
+    }
+}
+
+impl Network {
+    pub fn from_bytes(data: &[u8]) -> io::Result<Network> {
+        let mut reader = NetReader { data };
+        if reader.take(4)? != NNUE_MAGIC {
+            return Err(invalid_data("not a network file"));
+        }
+        if reader.u32()? != NNUE_VERSION {
+            return Err(invalid_data("unsupported version"));
+        }
+        if reader.u32()? != FEATURE_SET_HALFKA {
+            return Err(invalid_data("unsupported feature set"));
+        }
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN || !hidden.is_multiple_of(32) {
+            return Err(invalid_data("hidden size must be a multiple of 32"));
+        }
+        let network = Network {
+            hidden,
+            scale: reader.i32()?,
+            ft_biases: reader.i16s(hidden)?,
+            ft_weights: reader.i16s(hidden * NNUE_FEATURES)?,
+            out_weights: reader.i8s(2 * hidden)?,
+            out_bias: reader.i32()?,
+        };
+        if !reader.data.is_empty() {
+            return Err(invalid_data("trailing data after the network"));
+        }
+        Ok(network)
+    }
+
+    pub fn load(path: &str) -> io::Result<Network> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Network::from_bytes(&data)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(2 * self.ft_weights.len() + 3 * self.hidden + 24);
+        data.extend_from_slice(&NNUE_MAGIC);
+        data.extend_from_slice(&NNUE_VERSION.to_le_bytes());
+        data.extend_from_slice(&FEATURE_SET_HALFKA.to_le_bytes());
+        data.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        data.extend_from_slice(&self.scale.to_le_bytes());
+        for x in self.ft_biases.iter().chain(self.ft_weights.iter()) {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        data.extend(self.out_weights.iter().map(|x| *x as u8));
+        data.extend_from_slice(&self.out_bias.to_le_bytes());
+        data
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        File::create(path)?.write_all(&self.to_bytes())
+    }
+
+    // the piece-square tables as a net, see the note at the top of the file
+    pub fn embedded() -> Network {
+        let hidden = EMBEDDED_HIDDEN;
+        let mut ft_weights = vec![0; hidden * NNUE_FEATURES];
+        let mut king: usize = 0;
+        while king < 64 {
+            // own pieces only, the enemy's are counted by the other perspective
+            let mut piece: usize = 0;
+            while piece < 6 {
+                let mut square: usize = 0;
+                while square < 64 {
+                    let value = PST[piece][square];
+                    let value = (value.mg + value.eg) / 2;
+                    // one neuron per piece type and file, so that they rarely clip
+                    let neuron = 8 * piece + COLS[square];
+                    let feature = 64 * 12 * king + 64 * piece + square;
+                    ft_weights[feature * hidden + neuron] =
+                        ((value + EMBEDDED_UNIT / 2).div_euclid(EMBEDDED_UNIT)) as i16;
+                    square += 1;
+                }
+                piece += 1;
+            }
+            king += 1;
+        }
+        let mut ft_biases = vec![0; hidden];
+        let mut out_weights = vec![0; 2 * hidden];
+        let mut i: usize = 0;
+        while i < 48 {
+            ft_biases[i] = EMBEDDED_BIAS;
+            out_weights[i] = QB as i8;
+            out_weights[hidden + i] = -QB as i8;
+            i += 1;
+        }
+        Network {
+            hidden,
+            scale: EMBEDDED_UNIT * QA,
+            ft_biases,
+            ft_weights,
+            out_weights,
+            out_bias: 0,
+        }
+    }
+
+    pub fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.ft_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    // centipawns from the side to move's point of view
+    pub fn forward(&self, us: &[i16], them: &[i16]) -> i32 {
+        let (us_weights, them_weights) = self.out_weights.split_at(self.hidden);
+        let sum = crelu_dot(us, us_weights) as i64
+            + crelu_dot(them, them_weights) as i64
+            + self.out_bias as i64;
+        (sum * self.scale as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+/* the vector code: accumulator updates and the output layer, with avx2 versions picked at
+run time and scalar versions for everything else.
+
+note: lengths are multiples of 32, which the network loader checks. */
+
+pub fn add_weights_scalar(acc: &mut [i16], weights: &[i16]) {
+    for (x, w) in acc.iter_mut().zip(weights.iter()) {
+        *x = x.wrapping_add(*w);
+    }
+}
+
+pub fn sub_weights_scalar(acc: &mut [i16], weights: &[i16]) {
+    for (x, w) in acc.iter_mut().zip(weights.iter()) {
+        *x = x.wrapping_sub(*w);
+    }
+}
+
+pub fn crelu_dot_scalar(acc: &[i16], weights: &[i8]) -> i32 {
+    let mut sum: i32 = 0;
+    for (x, w) in acc.iter().zip(weights.iter()) {
+        sum += (*x as i32).clamp(0, QA) * *w as i32;
+    }
+    sum
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn add_weights_avx2(acc: &mut [i16], weights: &[i16]) {
+    use std::arch::x86_64::*;
+    let mut i: usize = 0;
+    while i + 16 <= acc.len() {
+        let x = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+        let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+        _mm256_storeu_si256(acc.as_mut_ptr().add(i) as *mut __m256i, _mm256_add_epi16(x, w));
+        i += 16;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sub_weights_avx2(acc: &mut [i16], weights: &[i16]) {
+    use std::arch::x86_64::*;
+    let mut i: usize = 0;
+    while i + 16 <= acc.len() {
+        let x = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+        let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+        _mm256_storeu_si256(acc.as_mut_ptr().add(i) as *mut __m256i, _mm256_sub_epi16(x, w));
+        i += 16;
+    }
+}
+
+// the int8 weights are widened to int16 and multiplied with madd, which can not saturate
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn crelu_dot_avx2(acc: &[i16], weights: &[i8]) -> i32 {
+    use std::arch::x86_64::*;
+    let zero = _mm256_setzero_si256();
+    let qa = _mm256_set1_epi16(QA as i16);
+    let mut sum = _mm256_setzero_si256();
+    let mut i: usize = 0;
+    while i + 16 <= acc.len() {
+        let x = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+        let x = _mm256_min_epi16(_mm256_max_epi16(x, zero), qa);
+        let w = _mm256_cvtepi8_epi16(_mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i));
+        sum = _mm256_add_epi32(sum, _mm256_madd_epi16(x, w));
+        i += 16;
+    }
+    let sum = _mm_add_epi32(_mm256_castsi256_si128(sum), _mm256_extracti128_si256(sum, 1));
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+    _mm_cvtsi128_si32(sum)
+}
+
+pub fn add_weights(acc: &mut [i16], weights: &[i16]) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { add_weights_avx2(acc, weights) };
+    }
+    add_weights_scalar(acc, weights)
+}
+
+pub fn sub_weights(acc: &mut [i16], weights: &[i16]) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { sub_weights_avx2(acc, weights) };
+    }
+    sub_weights_scalar(acc, weights)
+}
+
+pub fn crelu_dot(acc: &[i16], weights: &[i8]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { crelu_dot_avx2(acc, weights) };
+    }
+    crelu_dot_scalar(acc, weights)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    // hash of the position the accumulator is for
+    pub hash: u64,
+    // king squares of white and black
+    pub kings: [usize; 2],
+    // white's and black's perspective
+    pub values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    pub fn new(hidden: usize) -> Accumulator {
+        Accumulator { hash: 0, kings: [0; 2], values: [vec![0; hidden], vec![0; hidden]] }
+    }
+
+    // the perspective of side recomputed from scratch
+    pub fn refresh(&mut self, network: &Network, chessboard: &ChessBoard, side: Side) {
+        let king = king_square(chessboard, side);
+        let values = &mut self.values[side as usize];
+        values.copy_from_slice(&network.ft_biases);
+        let mut piece: usize = 0;
+        while piece < 12 {
+            let mut pieces = chessboard.piece_bbs[piece];
+            while let Some(square) = pieces.lsb_index() {
+                pieces = pieces.pop_bit(square);
+                let feature = feature_index(side, king, piece, square);
+                add_weights(values, network.feature_weights(feature));
+            }
+            piece += 1;
+        }
+        self.kings[side as usize] = king;
+        self.hash = chessboard.current_hash;
+    }
+
+    pub fn refresh_all(&mut self, network: &Network, chessboard: &ChessBoard) {
+        self.refresh(network, chessboard, Side::White);
+        self.refresh(network, chessboard, Side::Black);
+    }
+
+    // the accumulator of after, from parent, the accumulator of before
+    pub fn update(
+        &mut self,
+        network: &Network,
+        parent: &Accumulator,
+        before: &ChessBoard,
+        after: &ChessBoard,
+    ) {
+        for side in [Side::White, Side::Black] {
+            let s = side as usize;
+            let king = king_square(after, side);
+            if king != parent.kings[s] {
+                self.refresh(network, after, side);
+                continue;
+            }
+            let values = &mut self.values[s];
+            values.copy_from_slice(&parent.values[s]);
+            let mut piece: usize = 0;
+            while piece < 12 {
+                let old = before.piece_bbs[piece].data;
+                let new = after.piece_bbs[piece].data;
+                let mut removed = BB { data: old & !new };
+                while let Some(square) = removed.lsb_index() {
+                    removed = removed.pop_bit(square);
+                    let feature = feature_index(side, king, piece, square);
+                    sub_weights(values, network.feature_weights(feature));
+                }
+                let mut added = BB { data: new & !old };
+                while let Some(square) = added.lsb_index() {
+                    added = added.pop_bit(square);
+                    let feature = feature_index(side, king, piece, square);
+                    add_weights(values, network.feature_weights(feature));
+                }
+                piece += 1;
+            }
+            self.kings[s] = king;
+        }
+        self.hash = after.current_hash;
+    }
+}
+
+// note: accumulators[..len] are the stack of the positions being searched, the ones above
+//       are kept for their allocations. a position that does not match the top of the stack
+//       (the root of a new search) refreshes it.
+#[derive(Debug, Clone)]
+pub struct NnueEval {
+    pub network: Arc<Network>,
+    pub accumulators: Vec<Accumulator>,
+    pub len: usize,
+}
+
+impl NnueEval {
+    pub fn new(network: Arc<Network>) -> NnueEval {
+        NnueEval { network, accumulators: Vec::new(), len: 0 }
+    }
+
+    // makes the top of the stack the accumulator of chessboard
+    fn sync(&mut self, chessboard: &ChessBoard) {
+        if self.len == 0 {
+            if self.accumulators.is_empty() {
+                self.accumulators.push(Accumulator::new(self.network.hidden));
+            }
+            self.len = 1;
+        } else if self.accumulators[self.len - 1].hash == chessboard.current_hash {
+            return;
+        }
+        self.accumulators[self.len - 1].refresh_all(&self.network, chessboard);
+    }
+}
+
+impl Evaluator for NnueEval {
+    fn evaluate(&mut self, chessboard: &ChessBoard) -> Score {
+        self.sync(chessboard);
+        let values = &self.accumulators[self.len - 1].values;
+        let (us, them) = match chessboard.side_to_move {
+            Side::White => (&values[0], &values[1]),
+            Side::Black => (&values[1], &values[0]),
+        };
+        let bound = Score::MATE_BOUND.data - 1;
+        Score::new(self.network.forward(us, them).clamp(-bound, bound))
+    }
+
+    fn on_make(&mut self, before: &ChessBoard, _chess_move: Option<ChessMove>, after: &ChessBoard) {
+        self.sync(before);
+        if self.accumulators.len() == self.len {
+            self.accumulators.push(Accumulator::new(self.network.hidden));
+        }
+        let (stack, rest) = self.accumulators.split_at_mut(self.len);
+        rest[0].update(&self.network, &stack[self.len - 1], before, after);
+        self.len += 1;
+    }
+
+    fn on_unmake(&mut self) {
+        self.len -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::bench_positions;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // a net with random weights, so that every feature counts
+    fn random_network(seed: u64) -> Network {
+        let hidden = 32;
+        let mut rng = StdRng::seed_from_u64(seed);
+        Network {
+            hidden,
+            scale: 400,
+            ft_biases: (0..hidden).map(|_| rng.gen_range(-64..64)).collect(),
+            ft_weights: (0..hidden * NNUE_FEATURES).map(|_| rng.gen_range(-64..64)).collect(),
+            out_weights: (0..2 * hidden).map(|_| rng.gen()).collect(),
+            out_bias: rng.gen_range(-1000..1000),
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let network = random_network(1);
+        let bytes = network.to_bytes();
+        // the header, then the sizes of the documented format
+        assert_eq!(bytes[0..4], NNUE_MAGIC);
+        assert_eq!(bytes[4..8], NNUE_VERSION.to_le_bytes());
+        assert_eq!(bytes[8..12], FEATURE_SET_HALFKA.to_le_bytes());
+        assert_eq!(bytes[12..16], 32u32.to_le_bytes());
+        assert_eq!(bytes[16..20], 400i32.to_le_bytes());
+        assert_eq!(bytes.len(), 20 + 2 * 32 + 2 * 32 * NNUE_FEATURES + 2 * 32 + 4);
+        assert_eq!(bytes[20..22], network.ft_biases[0].to_le_bytes());
+        assert_eq!(bytes[bytes.len() - 4..], network.out_bias.to_le_bytes());
+
+        let path = std::env::temp_dir().join("stowockfish_nnets_test.nnue");
+        let path = path.to_str().unwrap().to_string();
+        network.save(&path).unwrap();
+        let loaded = Network::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), network);
+
+        // one byte short or one byte too many
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(Network::from_bytes(&longer).is_err());
+    }
+
+    // checks the incremental accumulators against refreshed ones on every position up to
+    // depth plies from chessboard
+    fn check_incremental(evaluator: &mut NnueEval, chessboard: &ChessBoard, depth: usize) {
+        let score = evaluator.evaluate(chessboard);
+        let mut fresh = Accumulator::new(evaluator.network.hidden);
+        fresh.refresh_all(&evaluator.network, chessboard);
+        assert_eq!(evaluator.accumulators[evaluator.len - 1], fresh, "{}", chessboard.to_fen());
+        assert_eq!(score, NnueEval::new(Arc::clone(&evaluator.network)).evaluate(chessboard));
+        if depth == 0 {
+            return;
+        }
+        let moves_array = chessboard.generate_moves();
+        let mut i: usize = 0;
+        while i < moves_array.len() {
+            let new_chessboard = chessboard.update_state(moves_array.data[i].unwrap());
+            evaluator.on_make(chessboard, moves_array.data[i], &new_chessboard);
+            check_incremental(evaluator, &new_chessboard, depth - 1);
+            evaluator.on_unmake();
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match_a_refresh() {
+        // the bench positions have castling, en passant and promotions to play
+        let mut evaluator = NnueEval::new(Arc::new(random_network(2)));
+        for chessboard in bench_positions().iter() {
+            check_incremental(&mut evaluator, chessboard, 2);
+        }
+    }
+
+    #[test]
+    fn vector_code_matches_the_scalar_code() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for length in [32, 64, 512] {
+            let mut i: usize = 0;
+            while i < 100 {
+                let acc: Vec<i16> = (0..length).map(|_| rng.gen()).collect();
+                let weights: Vec<i16> = (0..length).map(|_| rng.gen()).collect();
+                let out_weights: Vec<i8> = (0..length).map(|_| rng.gen()).collect();
+
+                let (mut x, mut y) = (acc.clone(), acc.clone());
+                add_weights(&mut x, &weights);
+                add_weights_scalar(&mut y, &weights);
+                assert_eq!(x, y);
+                sub_weights(&mut x, &weights);
+                sub_weights_scalar(&mut y, &weights);
+                assert_eq!(x, y);
+                assert_eq!(x, acc);
+                assert_eq!(crelu_dot(&acc, &out_weights), crelu_dot_scalar(&acc, &out_weights));
+                i += 1;
+            }
+        }
+    }
+}
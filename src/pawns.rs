@@ -0,0 +1,291 @@
+#![allow(dead_code)]
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::king::*;
+
+/* pawn structure evaluation. the terms that only depend on the pawns (doubled, isolated,
+backward, connected and passed pawns) are cached in a pawn hash table keyed by
+ChessBoard.pawn_hash. the passed pawn terms that depend on the other pieces (free path,
+king distance, square rule) are added on top of the cached entry.
+
+note: files are as in COLS, h is 0 and a is 7. */
+
+pub const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+pub const FILE_MASKS: [BB; 8] = init_file_masks();
+pub const ADJACENT_FILES: [BB; 8] = init_adjacent_files();
+// squares in front of a pawn on its file, for white and black pawns
+pub const FRONT_SPANS: [[BB; 64]; 2] = init_front_spans();
+// squares that must be free of enemy pawns for a pawn to be passed
+pub const PASSED_MASKS: [[BB; 64]; 2] = init_passed_masks();
+// squares of the adjacent files on the rank of a pawn and behind it, where its supporters
+// are or could come from
+pub const SUPPORT_SPANS: [[BB; 64]; 2] = init_support_spans();
+
+const fn init_file_masks() -> [BB; 8] {
+    let mut masks = [BB::ZERO; 8];
+    let mut i: usize = 0;
+    while i < 8 {
+        masks[i] = BB { data: 0x0101010101010101 << i };
+        i += 1;
+    }
+    masks
+}
+
+const fn init_adjacent_files() -> [BB; 8] {
+    let files = init_file_masks();
+    let mut masks = [BB::ZERO; 8];
+    let mut i: usize = 0;
+    while i < 8 {
+        if i > 0 {
+            masks[i].data |= files[i - 1].data;
+        }
+        if i < 7 {
+            masks[i].data |= files[i + 1].data;
+        }
+        i += 1;
+    }
+    masks
+}
+
+// squares on ranks strictly in front of (or behind, if !forward) square for side
+const fn rank_span(side: Side, square: usize, forward: bool) -> u64 {
+    let row = ROWS[square];
+    let mut span: u64 = 0;
+    let mut i: usize = 0;
+    while i < 8 {
+        let ahead = match side {
+            Side::White => i > row,
+            Side::Black => i < row,
+        };
+        if ahead == forward && i != row {
+            span |= 0xff << (8 * i);
+        }
+        i += 1;
+    }
+    span
+}
+
+const fn init_front_spans() -> [[BB; 64]; 2] {
+    let files = init_file_masks();
+    let mut spans = [[BB::ZERO; 64]; 2];
+    let mut i: usize = 0;
+    while i < 64 {
+        spans[0][i].data = files[COLS[i]].data & rank_span(Side::White, i, true);
+        spans[1][i].data = files[COLS[i]].data & rank_span(Side::Black, i, true);
+        i += 1;
+    }
+    spans
+}
+
+const fn init_passed_masks() -> [[BB; 64]; 2] {
+    let files = init_file_masks();
+    let adjacent = init_adjacent_files();
+    let mut masks = [[BB::ZERO; 64]; 2];
+    let mut i: usize = 0;
+    while i < 64 {
+        let span = files[COLS[i]].data | adjacent[COLS[i]].data;
+        masks[0][i].data = span & rank_span(Side::White, i, true);
+        masks[1][i].data = span & rank_span(Side::Black, i, true);
+        i += 1;
+    }
+    masks
+}
+
+const fn init_support_spans() -> [[BB; 64]; 2] {
+    let adjacent = init_adjacent_files();
+    let mut spans = [[BB::ZERO; 64]; 2];
+    let mut i: usize = 0;
+    while i < 64 {
+        let row = 0xffu64 << (8 * ROWS[i]);
+        spans[0][i].data = adjacent[COLS[i]].data & (row | rank_span(Side::White, i, false));
+        spans[1][i].data = adjacent[COLS[i]].data & (row | rank_span(Side::Black, i, false));
+        i += 1;
+    }
+    spans
+}
+
+// rank of square as seen from side, 0 being its back rank
+pub const fn relative_rank(side: Side, square: usize) -> usize {
+    match side {
+        Side::White => ROWS[square],
+        Side::Black => 7 - ROWS[square],
+    }
+}
+
+pub const fn promotion_square(side: Side, square: usize) -> usize {
+    match side {
+        Side::White => 56 + COLS[square],
+        Side::Black => COLS[square],
+    }
+}
+
+// king moves between two squares
+pub const fn distance(a: usize, b: usize) -> i32 {
+    let rows = ROWS[a].abs_diff(ROWS[b]);
+    let cols = COLS[a].abs_diff(COLS[b]);
+    if rows > cols {
+        rows as i32
+    } else {
+        cols as i32
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub key: u64,
+    // pawn-only terms, from white's point of view
+    pub score: TaperedScore,
+    // passed pawns of white and black
+    pub passed: [BB; 2],
+}
+
+// note: an empty slot has key 0, which is also the key of a position without pawns. the
+//       empty entry is the correct one for it.
+#[derive(Debug, Clone)]
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnEntry {
+    pub const EMPTY: PawnEntry =
+        PawnEntry { key: 0, score: TaperedScore::ZERO, passed: [BB::ZERO; 2] };
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        PawnTable { entries: vec![PawnEntry::EMPTY; PAWN_TABLE_SIZE] }
+    }
+}
+
+impl PawnTable {
+    pub fn probe(&self, key: u64) -> Option<PawnEntry> {
+        let entry = self.entries[(key as usize) & (PAWN_TABLE_SIZE - 1)];
+        match entry.key == key {
+            true => Some(entry),
+            false => None,
+        }
+    }
+
+    pub fn store(&mut self, entry: PawnEntry) {
+        self.entries[(entry.key as usize) & (PAWN_TABLE_SIZE - 1)] = entry;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PawnEntry::EMPTY);
+    }
+}
+
+// the pawn-only terms of side's pawns, from side's point of view
+pub fn pawn_terms(side: Side, own: BB, enemy: BB, params: &EvalParams) -> (TaperedScore, BB) {
+    let s = side as usize;
+    let mut score = TaperedScore::ZERO;
+    let mut passed = BB::ZERO;
+    let mut pawns = own;
+    while let Some(square) = pawns.lsb_index() {
+        pawns = pawns.pop_bit(square);
+        let col = COLS[square];
+        let rank = relative_rank(side, square);
+        // own pawns defending the pawn, enemy pawns attacking its stop square
+        let (supporters, enemy_attacks) = match side {
+            Side::White => (B_PAWN_ATTACKS[square], W_PAWN_ATTACKS[square + 8]),
+            Side::Black => (W_PAWN_ATTACKS[square], B_PAWN_ATTACKS[square - 8]),
+        };
+        let phalanx = ADJACENT_FILES[col].data & (0xffu64 << (8 * ROWS[square]));
+
+        let doubled = own.data & FRONT_SPANS[s][square].data != 0;
+        if doubled {
+            score += params.doubled_pawn;
+        }
+        if own.data & ADJACENT_FILES[col].data == 0 {
+            score += params.isolated_pawn;
+        } else if own.data & SUPPORT_SPANS[s][square].data == 0
+            && enemy.data & enemy_attacks.data != 0
+        {
+            // can not be defended by a pawn, and can not advance safely
+            score += params.backward_pawn;
+        }
+        if own.data & (supporters.data | phalanx) != 0 {
+            score += params.connected_pawn[rank];
+        }
+        if !doubled && enemy.data & PASSED_MASKS[s][square].data == 0 {
+            score += params.passed_pawn[rank];
+            passed.data |= 1u64 << square;
+        }
+    }
+    (score, passed)
+}
+
+pub fn pawn_entry(chessboard: &ChessBoard, params: &EvalParams) -> PawnEntry {
+    let white = chessboard.piece_bbs[5];
+    let black = chessboard.piece_bbs[11];
+    let (white_score, white_passed) = pawn_terms(Side::White, white, black, params);
+    let (black_score, black_passed) = pawn_terms(Side::Black, black, white, params);
+    PawnEntry {
+        key: chessboard.pawn_hash,
+        score: white_score - black_score,
+        passed: [white_passed, black_passed],
+    }
+}
+
+// the passed pawn terms that depend on the other pieces, from side's point of view
+pub fn passed_terms(
+    chessboard: &ChessBoard,
+    side: Side,
+    passed: BB,
+    params: &EvalParams,
+) -> TaperedScore {
+    let s = side as usize;
+    let enemy = side.update();
+    let own_king = king_square(chessboard, side);
+    let enemy_king = king_square(chessboard, enemy);
+    let occupied = chessboard.blockers();
+    let pawn_ending = !chessboard.has_non_pawn_material(enemy);
+
+    let mut score = TaperedScore::ZERO;
+    let mut pawns = passed;
+    while let Some(square) = pawns.lsb_index() {
+        pawns = pawns.pop_bit(square);
+        let rank = relative_rank(side, square);
+        let promotion = promotion_square(side, square);
+        let is_free = occupied.data & FRONT_SPANS[s][square].data == 0;
+        if is_free {
+            score += params.passed_free[rank];
+        }
+        // the king distances matter more the further the pawn is
+        let weight = rank.saturating_sub(2) as i32;
+        score += params.passed_own_king * (distance(own_king, promotion) * weight);
+        score += params.passed_enemy_king * (distance(enemy_king, promotion) * weight);
+
+        // square rule: the enemy king can not catch the pawn
+        if pawn_ending && is_free {
+            let pawn_moves = (7 - rank as i32) - (rank == 1) as i32;
+            let king_moves =
+                distance(enemy_king, promotion) - (chessboard.side_to_move == enemy) as i32;
+            if king_moves > pawn_moves {
+                score += params.passed_unstoppable;
+            }
+        }
+    }
+    score
+}
+
+// pawn structure from white's point of view
+pub fn evaluate_pawns(
+    chessboard: &ChessBoard,
+    params: &EvalParams,
+    table: &mut PawnTable,
+) -> TaperedScore {
+    let entry = match table.probe(chessboard.pawn_hash) {
+        Some(x) => x,
+        None => {
+            let entry = pawn_entry(chessboard, params);
+            table.store(entry);
+            entry
+        }
+    };
+    entry.score + passed_terms(chessboard, Side::White, entry.passed[0], params)
+        - passed_terms(chessboard, Side::Black, entry.passed[1], params)
+}
@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::eval::*;
+use crate::king::*;
+use crate::pawns::*;
+
+/* mobility and piece activity, from the attack tables of the move generator.
+
+note: mobility only counts safe squares, those not taken by own pieces and not attacked
+      by enemy pawns. */
+
+// squares attacked by side's pawns
+pub const fn pawn_attacks(chessboard: &ChessBoard, side: Side) -> BB {
+    let mut attacks = BB::ZERO;
+    let mut pawns = match side {
+        Side::White => chessboard.piece_bbs[5],
+        Side::Black => chessboard.piece_bbs[11],
+    };
+    while let Some(square) = pawns.lsb_index() {
+        pawns = pawns.pop_bit(square);
+        attacks.data |= match side {
+            Side::White => W_PAWN_ATTACKS[square].data,
+            Side::Black => B_PAWN_ATTACKS[square].data,
+        };
+    }
+    attacks
+}
+
+// pieces of side, all of them
+pub const fn side_pieces(chessboard: &ChessBoard, side: Side) -> BB {
+    let o = 6 * side as usize;
+    let mut data: u64 = 0;
+    let mut i: usize = 0;
+    while i < 6 {
+        data |= chessboard.piece_bbs[o + i].data;
+        i += 1;
+    }
+    BB { data }
+}
+
+// the safe squares for side's pieces
+pub const fn mobility_area(chessboard: &ChessBoard, side: Side) -> BB {
+    let own = side_pieces(chessboard, side);
+    let enemy_pawn_attacks = pawn_attacks(chessboard, side.update());
+    BB { data: !own.data & !enemy_pawn_attacks.data }
+}
+
+// the number of safe squares a piece on square attacks
+const fn safe_squares(piece_type: PieceType, square: usize, occupied: BB, area: BB) -> usize {
+    let attacks = match piece_type {
+        PieceType::Knight => KNIGHT_ATTACKS[square],
+        PieceType::Bishop => get_bishop_attack(square, occupied),
+        PieceType::Rook => get_rook_attack(square, occupied),
+        PieceType::Queen => get_queen_attack(square, occupied),
+        _ => BB::ZERO,
+    };
+    (attacks.data & area.data).count_ones() as usize
+}
+
+// mobility of side's pieces, from side's point of view
+pub fn mobility(chessboard: &ChessBoard, side: Side, params: &EvalParams) -> TaperedScore {
+    let o = 6 * side as usize;
+    let occupied = chessboard.blockers();
+    let area = mobility_area(chessboard, side);
+    let mut score = TaperedScore::ZERO;
+    let mut pieces = [
+        (PieceType::Queen, chessboard.piece_bbs[o + 1]),
+        (PieceType::Knight, chessboard.piece_bbs[o + 2]),
+        (PieceType::Bishop, chessboard.piece_bbs[o + 3]),
+        (PieceType::Rook, chessboard.piece_bbs[o + 4]),
+    ];
+    for (piece_type, bb) in pieces.iter_mut() {
+        while let Some(square) = bb.lsb_index() {
+            *bb = bb.pop_bit(square);
+            let count = safe_squares(*piece_type, square, occupied, area);
+            score += match piece_type {
+                PieceType::Queen => params.queen_mobility[count],
+                PieceType::Knight => params.knight_mobility[count],
+                PieceType::Bishop => params.bishop_mobility[count],
+                _ => params.rook_mobility[count],
+            };
+        }
+    }
+    score
+}
+
+// rook files, rooks on the 7th, bishop pair, outposts and trapped pieces, from side's point
+// of view
+pub fn piece_activity(chessboard: &ChessBoard, side: Side, params: &EvalParams) -> TaperedScore {
+    let s = side as usize;
+    let o = 6 * s;
+    let e = 6 - o;
+    let own_pawns = chessboard.piece_bbs[o + 5];
+    let enemy_pawns = chessboard.piece_bbs[e + 5];
+    let mut score = TaperedScore::ZERO;
+
+    if chessboard.piece_bbs[o + 3].data.count_ones() >= 2 {
+        score += params.bishop_pair;
+    }
+
+    let own_pawn_attacks = pawn_attacks(chessboard, side);
+    let mut knights = chessboard.piece_bbs[o + 2];
+    while let Some(square) = knights.lsb_index() {
+        knights = knights.pop_bit(square);
+        let rank = relative_rank(side, square);
+        // an outpost can never be chased away: no enemy pawn in front on the adjacent files
+        let attackers = PASSED_MASKS[s][square].data & ADJACENT_FILES[COLS[square]].data;
+        if (3..=5).contains(&rank)
+            && own_pawn_attacks.data & (1u64 << square) != 0
+            && enemy_pawns.data & attackers == 0
+        {
+            score += params.knight_outpost;
+        }
+    }
+
+    let king = king_square(chessboard, side);
+    let enemy_king = king_square(chessboard, side.update());
+    let occupied = chessboard.blockers();
+    let area = mobility_area(chessboard, side);
+    let can_castle = chessboard.castle_bools[2 * s] || chessboard.castle_bools[2 * s + 1];
+    let mut rooks = chessboard.piece_bbs[o + 4];
+    while let Some(square) = rooks.lsb_index() {
+        rooks = rooks.pop_bit(square);
+        let file = FILE_MASKS[COLS[square]].data;
+        if (own_pawns.data | enemy_pawns.data) & file == 0 {
+            score += params.rook_open_file;
+        } else if own_pawns.data & file == 0 {
+            score += params.rook_semi_open_file;
+        }
+        // on the 7th, with enemy pawns to attack or the king cut off on the 8th
+        let seventh = 0xffu64 << (8 * ROWS[square]);
+        if relative_rank(side, square) == 6
+            && (enemy_pawns.data & seventh != 0 || relative_rank(side, enemy_king) == 7)
+        {
+            score += params.rook_on_seventh;
+        }
+        // between the king and the corner, with the king unable to castle out of the way
+        let shut_in = match COLS[king] < 4 {
+            true => COLS[square] < COLS[king],
+            false => COLS[square] > COLS[king],
+        };
+        if !can_castle
+            && relative_rank(side, king) == 0
+            && ROWS[square] == ROWS[king]
+            && shut_in
+            && safe_squares(PieceType::Rook, square, occupied, area) <= 3
+        {
+            score += params.trapped_rook;
+        }
+    }
+
+    // a bishop on a7 or h7 (a2 or h2 for black) shut in by a pawn on b6 or g6 (b3 or g3)
+    let flip = if s == 0 { 0 } else { 56 };
+    let bishops = chessboard.piece_bbs[o + 3].data;
+    for (bishop, pawn) in [(55, 46), (48, 41)] {
+        if bishops & (1u64 << (bishop ^ flip)) != 0
+            && enemy_pawns.data & (1u64 << (pawn ^ flip)) != 0
+        {
+            score += params.trapped_bishop;
+        }
+    }
+    score
+}
+
+// mobility and piece activity from white's point of view
+pub fn evaluate_pieces(chessboard: &ChessBoard, params: &EvalParams) -> TaperedScore {
+    mobility(chessboard, Side::White, params) - mobility(chessboard, Side::Black, params)
+        + piece_activity(chessboard, Side::White, params)
+        - piece_activity(chessboard, Side::Black, params)
+}
@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+
+use std::fmt::Display;
+use std::ops::{Add, Neg, Sub};
+
+use crate::search::MAX_PLY;
+
+/* scores are from the point of view of the side to move, in centipawns.
+
+note: mate scores are encoded relative to the root of the search:
+    Score::MATE - ply  the side to move mates in ply plies from the root
+   -Score::MATE + ply  the side to move is mated in ply plies from the root
+anything further than MAX_PLY plies away from a mate is a normal evaluation. */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Score {
+    pub data: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score { data: 0 };
+    pub const MATE: Score = Score { data: 32000 };
+    pub const INFINITY: Score = Score { data: 32001 };
+    // scores with an absolute value of at least this are mates
+    pub const MATE_BOUND: Score = Score { data: 32000 - MAX_PLY as i32 };
+
+    pub const fn new(data: i32) -> Score {
+        Score { data }
+    }
+
+    pub const fn mate_in(ply: usize) -> Score {
+        Score { data: Score::MATE.data - ply as i32 }
+    }
+
+    pub const fn mated_in(ply: usize) -> Score {
+        Score { data: -Score::MATE.data + ply as i32 }
+    }
+
+    pub const fn is_mate(&self) -> bool {
+        self.data >= Score::MATE_BOUND.data || self.data <= -Score::MATE_BOUND.data
+    }
+
+    // full moves until mate as used by uci "score mate N", negative if the side to move is mated
+    pub const fn mate_moves(&self) -> Option<i32> {
+        if self.data >= Score::MATE_BOUND.data {
+            Some((Score::MATE.data - self.data + 1) / 2)
+        } else if self.data <= -Score::MATE_BOUND.data {
+            Some(-(Score::MATE.data + self.data) / 2)
+        } else {
+            None
+        }
+    }
+
+    // the transposition table stores mates relative to the node instead of the root,
+    // so that an entry stays correct when the position is reached at a different ply
+    pub const fn adjust_to_tt(self, ply: usize) -> Score {
+        if self.data >= Score::MATE_BOUND.data {
+            Score { data: self.data + ply as i32 }
+        } else if self.data <= -Score::MATE_BOUND.data {
+            Score { data: self.data - ply as i32 }
+        } else {
+            self
+        }
+    }
+
+    pub const fn adjust_from_tt(self, ply: usize) -> Score {
+        if self.data >= Score::MATE_BOUND.data {
+            Score { data: self.data - ply as i32 }
+        } else if self.data <= -Score::MATE_BOUND.data {
+            Score { data: self.data + ply as i32 }
+        } else {
+            self
+        }
+    }
+}
+
+// uci notation, "cp <x>" or "mate <n>"
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mate_moves() {
+            Some(n) => write!(f, "mate {}", n),
+            None => write!(f, "cp {}", self.data),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    fn neg(self) -> Self::Output {
+        Score { data: -self.data }
+    }
+}
+
+impl Add<i32> for Score {
+    type Output = Score;
+    fn add(self, rhs: i32) -> Self::Output {
+        Score { data: self.data + rhs }
+    }
+}
+
+impl Sub<i32> for Score {
+    type Output = Score;
+    fn sub(self, rhs: i32) -> Self::Output {
+        Score { data: self.data - rhs }
+    }
+}
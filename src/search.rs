@@ -0,0 +1,1407 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::chessmove::*;
+use crate::eval::*;
+use crate::score::*;
+use crate::stats::*;
+use crate::ttable::*;
+use crate::SEARCH_STACK_SIZE;
+
+pub const MAX_DEPTH: usize = 64;
+// plies the search can reach from the root, including extensions
+pub const MAX_PLY: usize = 128;
+// depth used when "go" is given nothing to limit the search with
+const DEFAULT_DEPTH: usize = 6;
+// milliseconds kept in reserve for gui/communication lag
+const MOVE_OVERHEAD: u64 = 30;
+// moves assumed to be left in the game when "movestogo" is not given
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+// the clock is only checked once every CHECK_INTERVAL nodes, must be a power of two
+const CHECK_INTERVAL: u64 = 2048;
+// iterations from this depth on start with a window of ASPIRATION_WINDOW around the last score
+const ASPIRATION_DEPTH: usize = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+// null-move pruning is tried from this depth on, and verified by a normal search at or
+// above NULL_VERIFY_DEPTH where a wrong cutoff would throw away a lot of work
+const NULL_MOVE_DEPTH: usize = 3;
+const NULL_VERIFY_DEPTH: usize = 10;
+// late move reductions start at the LMR_MOVES-th move searched (skipped and pruned moves do
+// not count) of nodes at least LMR_DEPTH deep
+const LMR_DEPTH: usize = 3;
+const LMR_MOVES: usize = 3;
+// late move pruning skips quiet moves beyond LMP_BASE + depth^2 at depths up to LMP_DEPTH
+const LMP_DEPTH: usize = 3;
+const LMP_BASE: usize = 3;
+// futility pruning skips quiet moves if the static eval is FUTILITY_MARGIN * depth below alpha
+const FUTILITY_DEPTH: usize = 3;
+const FUTILITY_MARGIN: i32 = 120;
+// singular extensions are tried from SINGULAR_DEPTH on, a tt move is singular if all other
+// moves fail low against its score minus SINGULAR_MARGIN * depth
+const SINGULAR_DEPTH: usize = 6;
+const SINGULAR_MARGIN: i32 = 2;
+// plies a single line can be extended by in total
+const MAX_EXTENSIONS: usize = 16;
+pub const MAX_THREADS: usize = 64;
+pub const MAX_MULTIPV: usize = 256;
+// mvv-lva values of pawn, knight, bishop, rook, queen and king
+const ORDER_PIECE_VALUES: [i32; 6] = [1, 3, 3, 5, 9, 10];
+
+// note: all times are in milliseconds, as sent by the gui.
+//       an empty searchmoves means that all root moves are searched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub movetime: Option<u64>,
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub mate: Option<usize>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub searchmoves: MovesArray,
+}
+
+impl SearchLimits {
+    // parses the arguments of a uci "go" command, unknown or malformed tokens are skipped
+    // note: searchmoves are only valid in chessboard, and end at the first token that is
+    //       not a legal move
+    pub fn from_uci(cmd_str: &str, chessboard: &ChessBoard) -> SearchLimits {
+        let mut limits = SearchLimits::default();
+        let mut cmds = cmd_str.split_whitespace().peekable();
+        while let Some(cmd) = cmds.next() {
+            match cmd {
+                "wtime" => limits.wtime = cmds.next().and_then(parse_time),
+                "btime" => limits.btime = cmds.next().and_then(parse_time),
+                "winc" => limits.winc = cmds.next().and_then(parse_time),
+                "binc" => limits.binc = cmds.next().and_then(parse_time),
+                "movestogo" => limits.movestogo = cmds.next().and_then(|x| x.parse().ok()),
+                "movetime" => limits.movetime = cmds.next().and_then(parse_time),
+                "depth" => limits.depth = cmds.next().and_then(|x| x.parse().ok()),
+                "nodes" => limits.nodes = cmds.next().and_then(|x| x.parse().ok()),
+                "mate" => limits.mate = cmds.next().and_then(|x| x.parse().ok()),
+                "infinite" => limits.infinite = true,
+                "ponder" => limits.ponder = true,
+                "searchmoves" => {
+                    while let Some(chess_move) =
+                        cmds.peek().and_then(|x| chessboard.find_uci_move(x))
+                    {
+                        if !limits.searchmoves.contains(chess_move) {
+                            limits.searchmoves.push(chess_move);
+                        }
+                        cmds.next();
+                    }
+                }
+                _ => {}
+            }
+        }
+        limits
+    }
+
+    pub const fn is_timed(&self) -> bool {
+        self.movetime.is_some() || self.wtime.is_some() || self.btime.is_some()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        match (self.depth, self.mate) {
+            (Some(depth), _) => depth.clamp(1, MAX_DEPTH),
+            (None, _) if self.infinite || self.is_timed() || self.nodes.is_some() => MAX_DEPTH,
+            // a mate in n moves is seen by a full width search of 2n plies
+            (None, Some(mate)) => (2 * mate).clamp(1, MAX_DEPTH),
+            (None, None) => DEFAULT_DEPTH,
+        }
+    }
+}
+
+// some guis send negative clock values when the engine is in time trouble
+fn parse_time(s: &str) -> Option<u64> {
+    s.parse::<i64>().ok().map(|x| x.max(0) as u64)
+}
+
+// shared between the uci thread and the search threads
+#[derive(Debug, Default)]
+pub struct SearchSignals {
+    pub stop: AtomicBool,
+    pub ponder: AtomicBool,
+    // nodes searched by all threads, each thread adds its count every CHECK_INTERVAL nodes
+    pub nodes: AtomicU64,
+}
+
+impl SearchSignals {
+    pub fn reset(&self, ponder: bool) {
+        self.stop.store(false, Ordering::SeqCst);
+        self.ponder.store(ponder, Ordering::SeqCst);
+        self.nodes.store(0, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.ponder.load(Ordering::Relaxed)
+    }
+}
+
+// soft limit: no new iteration is started once it has passed
+// hard limit: the running iteration is aborted once it has passed
+// note: neither limit applies while pondering, the clock starts on ponderhit
+#[derive(Debug, Copy, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    pondering: bool,
+}
+
+impl TimeManager {
+    pub fn new(limits: &SearchLimits, side: Side) -> TimeManager {
+        let start = Instant::now();
+        let pondering = limits.ponder;
+        if limits.infinite {
+            return TimeManager { start, soft_limit: None, hard_limit: None, pondering };
+        }
+
+        if let Some(movetime) = limits.movetime {
+            let limit = Duration::from_millis(movetime.saturating_sub(MOVE_OVERHEAD).max(1));
+            return TimeManager {
+                start,
+                soft_limit: Some(limit),
+                hard_limit: Some(limit),
+                pondering,
+            };
+        }
+
+        let (time, inc) = match side {
+            Side::White => (limits.wtime, limits.winc.unwrap_or(0)),
+            Side::Black => (limits.btime, limits.binc.unwrap_or(0)),
+        };
+
+        match time {
+            Some(time) => {
+                let usable = time.saturating_sub(MOVE_OVERHEAD).max(1);
+                let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, 50);
+                let soft = (usable / moves_to_go + inc * 3 / 4).min(usable);
+                let hard = (soft * 4).min(usable * 3 / 4).max(soft);
+                TimeManager {
+                    start,
+                    soft_limit: Some(Duration::from_millis(soft)),
+                    hard_limit: Some(Duration::from_millis(hard)),
+                    pondering,
+                }
+            }
+            None => TimeManager { start, soft_limit: None, hard_limit: None, pondering },
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // the opponent played the expected move, switch over to normal timing
+    pub fn ponderhit(&mut self) {
+        self.start = Instant::now();
+        self.pondering = false;
+    }
+
+    pub fn soft_expired(&self) -> bool {
+        if self.pondering {
+            return false;
+        }
+        match self.soft_limit {
+            Some(limit) => self.elapsed() >= limit,
+            None => false,
+        }
+    }
+
+    pub fn hard_expired(&self) -> bool {
+        if self.pondering {
+            return false;
+        }
+        match self.hard_limit {
+            Some(limit) => self.elapsed() >= limit,
+            None => false,
+        }
+    }
+}
+
+// a root move with its score and principal variation
+pub type PvLine = (ChessMove, Score, Vec<ChessMove>);
+
+// note: lines holds the MultiPV best root moves, ranked best first.
+//       best_move, score and pv are those of the first line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<ChessMove>,
+    pub ponder_move: Option<ChessMove>,
+    pub score: Score,
+    pub depth: usize,
+    pub seldepth: usize,
+    pub nodes: u64,
+    pub pv: Vec<ChessMove>,
+    pub lines: Vec<PvLine>,
+    pub stats: SearchStats,
+}
+
+impl SearchResult {
+    pub fn to_uci(&self) -> String {
+        match (self.best_move, self.ponder_move) {
+            (Some(best_move), Some(ponder_move)) => {
+                format!("bestmove {} ponder {}", best_move, ponder_move)
+            }
+            (Some(best_move), None) => format!("bestmove {}", best_move),
+            (None, _) => String::from("bestmove 0000"),
+        }
+    }
+}
+
+pub fn pv_string(pv: &[ChessMove]) -> String {
+    pv.iter().map(|x| format!("{}", x)).collect::<Vec<String>>().join(" ")
+}
+
+// note: the pruning toggles exist so that the impact of each technique can be measured
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SearchConfig {
+    // how much a draw is worth less than an equal position to the engine
+    pub contempt: i32,
+    pub lmr: bool,
+    pub lmp: bool,
+    pub futility: bool,
+    // extend pawn pushes to the 7th rank
+    pub pawn_extension: bool,
+    pub threads: usize,
+    // number of best root moves to search and report
+    pub multipv: usize,
+    // set by the uci "debug" command, search statistics are printed after each search
+    pub debug: bool,
+    // no info lines, for searches nobody is watching (datagen)
+    pub quiet: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            contempt: 0,
+            lmr: true,
+            lmp: true,
+            futility: true,
+            pawn_extension: true,
+            threads: 1,
+            multipv: 1,
+            debug: false,
+            quiet: false,
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn uci_options(&self) -> String {
+        [
+            format!("option name Contempt type spin default {} min -100 max 100", self.contempt),
+            format!("option name LateMoveReductions type check default {}", self.lmr),
+            format!("option name LateMovePruning type check default {}", self.lmp),
+            format!("option name FutilityPruning type check default {}", self.futility),
+            format!("option name PawnPushExtension type check default {}", self.pawn_extension),
+            format!(
+                "option name Threads type spin default {} min 1 max {}",
+                self.threads, MAX_THREADS
+            ),
+            format!(
+                "option name MultiPV type spin default {} min 1 max {}",
+                self.multipv, MAX_MULTIPV
+            ),
+        ]
+        .join("\n")
+    }
+
+    // returns false if the option is unknown or the value is invalid
+    pub fn set_option(&mut self, name: &str, value: &str) -> bool {
+        match name.to_ascii_lowercase().as_str() {
+            "contempt" => match value.parse::<i32>() {
+                Ok(x) => self.contempt = x.clamp(-100, 100),
+                Err(_) => return false,
+            },
+            "latemovereductions" => match value.parse::<bool>() {
+                Ok(x) => self.lmr = x,
+                Err(_) => return false,
+            },
+            "latemovepruning" => match value.parse::<bool>() {
+                Ok(x) => self.lmp = x,
+                Err(_) => return false,
+            },
+            "futilitypruning" => match value.parse::<bool>() {
+                Ok(x) => self.futility = x,
+                Err(_) => return false,
+            },
+            "pawnpushextension" => match value.parse::<bool>() {
+                Ok(x) => self.pawn_extension = x,
+                Err(_) => return false,
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(x) => self.threads = x.clamp(1, MAX_THREADS),
+                Err(_) => return false,
+            },
+            "multipv" => match value.parse::<usize>() {
+                Ok(x) => self.multipv = x.clamp(1, MAX_MULTIPV),
+                Err(_) => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+pub struct Searcher<'a, E: Evaluator> {
+    pub limits: SearchLimits,
+    pub config: SearchConfig,
+    pub tt: &'a TranspositionTable,
+    pub evaluator: &'a mut E,
+    pub timer: TimeManager,
+    pub signals: Arc<SearchSignals>,
+    pub nodes: u64,
+    // nodes already added to signals.nodes
+    flushed_nodes: u64,
+    pub completed_depth: usize,
+    pub stats: SearchStats,
+    // deepest ply reached in the current iteration
+    pub seldepth: usize,
+    pub stopped: bool,
+    // triangular pv table: pv_table[ply] is the best line found from ply onwards
+    pv_table: Vec<MovesArray>,
+    // principal variation of the previous iteration, searched first
+    prev_pv: MovesArray,
+    follow_pv: bool,
+    // two quiet moves per ply that recently caused a beta cutoff
+    killers: Vec<[Option<ChessMove>; 2]>,
+    // only the main thread talks to the gui, helpers search silently
+    is_main: bool,
+    // helpers start this many plies deeper, so that threads search different depths
+    depth_offset: usize,
+    // plies the line leading to each ply has been extended by
+    extensions: Vec<usize>,
+    // move skipped at each ply while testing the tt move for singularity
+    excluded: Vec<Option<ChessMove>>,
+    // root moves already reported on a better multipv line in this iteration
+    root_excluded: Vec<ChessMove>,
+}
+
+impl<'a, E: Evaluator> Searcher<'a, E> {
+    pub fn new(
+        limits: SearchLimits,
+        config: SearchConfig,
+        tt: &'a TranspositionTable,
+        evaluator: &'a mut E,
+        side: Side,
+        signals: Arc<SearchSignals>,
+        thread_id: usize,
+    ) -> Searcher<'a, E> {
+        Searcher {
+            limits,
+            config,
+            tt,
+            evaluator,
+            timer: TimeManager::new(&limits, side),
+            signals,
+            nodes: 0,
+            flushed_nodes: 0,
+            completed_depth: 0,
+            stats: SearchStats::default(),
+            seldepth: 0,
+            stopped: false,
+            pv_table: vec![MovesArray::new(); MAX_PLY + 1],
+            prev_pv: MovesArray::new(),
+            follow_pv: false,
+            killers: vec![[None; 2]; MAX_PLY + 1],
+            is_main: thread_id == 0,
+            depth_offset: thread_id % 2,
+            extensions: vec![0; MAX_PLY + 1],
+            excluded: vec![None; MAX_PLY + 1],
+            root_excluded: Vec::new(),
+        }
+    }
+
+    pub fn iterative_deepening(&mut self, chessboard: &ChessBoard) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: None,
+            ponder_move: None,
+            score: Score::ZERO,
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+            stats: SearchStats::default(),
+        };
+        let max_depth = self.limits.max_depth();
+        let root_moves = match self.limits.searchmoves.len() {
+            0 => chessboard.generate_moves().len(),
+            x => x,
+        };
+        let multipv = self.config.multipv.min(root_moves).max(1);
+        for depth in (1 + self.depth_offset).min(max_depth)..=max_depth {
+            self.seldepth = 0;
+            let (score, lines) = self.search_lines(chessboard, depth, multipv, &result.lines);
+            // results of an aborted iteration can not be trusted
+            if self.stopped {
+                break;
+            }
+            self.completed_depth = depth;
+            stat!(self.stats.iteration_nodes.push(self.nodes));
+
+            let pv = match lines.first() {
+                Some(x) => x.2.clone(),
+                None => Vec::new(),
+            };
+            self.prev_pv = MovesArray::new();
+            for chess_move in pv.iter() {
+                self.prev_pv.push(*chess_move);
+            }
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                ponder_move: pv.get(1).copied(),
+                score: lines.first().map_or(score, |x| x.1),
+                depth,
+                seldepth: self.seldepth,
+                nodes: self.nodes,
+                pv,
+                lines,
+                stats: SearchStats::default(),
+            };
+            if self.is_main {
+                self.print_info(&result);
+            }
+
+            // no legal moves at the root, nothing more to search
+            if result.best_move.is_none() {
+                break;
+            }
+
+            // "go mate n": a short enough forced mate has been proven
+            if let (Some(mate), Some(moves)) = (self.limits.mate, result.score.mate_moves()) {
+                if moves > 0 && moves as usize <= mate {
+                    break;
+                }
+            }
+
+            self.poll_ponderhit();
+            if self.timer.soft_expired() {
+                break;
+            }
+        }
+        self.flush_nodes();
+        result.nodes = self.nodes;
+        stat!(self.stats.nodes = self.nodes);
+        result.stats = self.stats.clone();
+        result
+    }
+
+    // searches the best multipv root moves one after another, each pass excluding the moves
+    // found by the earlier ones. returns the score of the first pass, which is the only one
+    // if there are no legal moves, and the lines ranked best first.
+    fn search_lines(
+        &mut self,
+        chessboard: &ChessBoard,
+        depth: usize,
+        multipv: usize,
+        prev_lines: &[PvLine],
+    ) -> (Score, Vec<PvLine>) {
+        self.root_excluded.clear();
+        let mut first_score = Score::ZERO;
+        let mut lines: Vec<PvLine> = Vec::new();
+        while lines.len() < multipv {
+            let prev = match prev_lines.get(lines.len()) {
+                Some(x) => x.1,
+                None => Score::ZERO,
+            };
+            let score = self.aspiration_search(chessboard, depth, prev);
+            if self.stopped {
+                break;
+            }
+            if lines.is_empty() {
+                first_score = score;
+            }
+            let pv = self.pv_table[0].to_vec();
+            match pv.first() {
+                Some(chess_move) => {
+                    self.root_excluded.push(*chess_move);
+                    lines.push((*chess_move, score, pv));
+                }
+                None => break,
+            }
+        }
+        self.root_excluded.clear();
+        // note: with pruning a later pass can score higher than an earlier one
+        lines.sort_by_key(|x| -x.1);
+        (first_score, lines)
+    }
+
+    // one info line per multipv line, nodes and nps are those of all threads together
+    pub fn print_info(&self, result: &SearchResult) {
+        if self.config.quiet {
+            return;
+        }
+        let nodes = self.total_nodes();
+        let elapsed = self.timer.elapsed().as_millis() as u64;
+        for (i, (_, score, pv)) in result.lines.iter().enumerate() {
+            println!(
+                "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                result.depth,
+                result.seldepth,
+                i + 1,
+                score,
+                nodes,
+                nodes * 1000 / elapsed.max(1),
+                elapsed,
+                pv_string(pv)
+            );
+        }
+        // the game is already over
+        if result.lines.is_empty() {
+            println!(
+                "info depth {} score {} nodes {} time {}",
+                result.depth, result.score, nodes, elapsed
+            );
+        }
+    }
+
+    fn flush_nodes(&mut self) {
+        self.signals.nodes.fetch_add(self.nodes - self.flushed_nodes, Ordering::Relaxed);
+        self.flushed_nodes = self.nodes;
+    }
+
+    pub fn total_nodes(&self) -> u64 {
+        self.signals.nodes.load(Ordering::Relaxed) + self.nodes - self.flushed_nodes
+    }
+
+    // searches the root with a narrow window around the previous score, the window is
+    // widened on the failing side until the score falls inside it
+    fn aspiration_search(&mut self, chessboard: &ChessBoard, depth: usize, prev: Score) -> Score {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match depth >= ASPIRATION_DEPTH && !prev.is_mate() {
+            true => (prev - delta, prev + delta),
+            false => (-Score::INFINITY, Score::INFINITY),
+        };
+        loop {
+            // the previous pv is only a good guess for the best line
+            self.follow_pv = self.root_excluded.is_empty();
+            let score = self.negamax(chessboard, alpha, beta, depth, 0, true);
+            if self.stopped {
+                return score;
+            }
+
+            if score <= alpha {
+                // fail low: also pull beta down, the score is likely to drop further
+                beta = Score::new((alpha.data + beta.data) / 2);
+                alpha = (score - delta).max(-Score::INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(Score::INFINITY);
+            } else {
+                return score;
+            }
+            delta += delta / 2;
+        }
+    }
+
+    fn poll_ponderhit(&mut self) {
+        if self.timer.pondering && !self.signals.is_pondering() {
+            self.timer.ponderhit();
+        }
+    }
+
+    // note: depth 1 is always completed so that there is a move to return
+    // note: the node limit is checked at every node, so that a single threaded search with
+    //       a node limit gives the same result on any machine
+    fn should_stop(&mut self) -> bool {
+        if self.nodes & (CHECK_INTERVAL - 1) == 0 {
+            self.flush_nodes();
+        }
+        if self.stopped || self.completed_depth == 0 {
+            return self.stopped;
+        }
+        if let Some(nodes) = self.limits.nodes {
+            self.stopped = self.total_nodes() >= nodes;
+        }
+        if !self.stopped && self.nodes & (CHECK_INTERVAL - 1) == 0 {
+            self.poll_ponderhit();
+            self.stopped = self.signals.is_stopped() || self.timer.hard_expired();
+        }
+        self.stopped
+    }
+
+    // root moves left out by "go searchmoves" or by an earlier multipv line
+    fn is_root_excluded(&self, chess_move: ChessMove) -> bool {
+        self.root_excluded.contains(&chess_move)
+            || (self.limits.searchmoves.len() > 0 && !self.limits.searchmoves.contains(chess_move))
+    }
+
+    // searches the previous iteration's pv move first while still on the pv,
+    // and the transposition table move first otherwise. the rest are ordered by
+    // mvv-lva captures, promotions, killers and then quiet moves.
+    fn order_moves(
+        &mut self,
+        chessboard: &ChessBoard,
+        moves_array: &mut MovesArray,
+        ply: usize,
+        tt_move: Option<ChessMove>,
+    ) {
+        let mut first_move = tt_move;
+        if self.follow_pv {
+            self.follow_pv = false;
+            if ply < self.prev_pv.len() && move_index(moves_array, self.prev_pv.data[ply]).is_some()
+            {
+                first_move = self.prev_pv.data[ply];
+                self.follow_pv = true;
+            }
+        }
+        let mut start: usize = 0;
+        if let Some(i) = move_index(moves_array, first_move) {
+            moves_array.data.swap(0, i);
+            start = 1;
+        }
+        let count = moves_array.len();
+        moves_array.data[start..count]
+            .sort_by_key(|x| -self.move_order_value(chessboard, x.unwrap(), ply));
+    }
+
+    fn move_order_value(&self, chessboard: &ChessBoard, chess_move: ChessMove, ply: usize) -> i32 {
+        let attacker = match chessboard.mailbox[chess_move.source()] {
+            Some((_, piece_type)) => ORDER_PIECE_VALUES[piece_type as usize],
+            None => panic!("move_order_value error: no piece on the source square!"),
+        };
+        let victim = match chessboard.mailbox[chess_move.target()] {
+            Some((_, piece_type)) => ORDER_PIECE_VALUES[piece_type as usize],
+            None => ORDER_PIECE_VALUES[0], // en passant
+        };
+        if chessboard.is_capture(chess_move) {
+            30000 + victim * 100 - attacker
+        } else if let Some(piece_type) = chess_move.get_piece_data() {
+            20000 + ORDER_PIECE_VALUES[piece_type as usize]
+        } else if self.killers[ply][0] == Some(chess_move) {
+            10001
+        } else if self.killers[ply][1] == Some(chess_move) {
+            10000
+        } else {
+            0
+        }
+    }
+
+    fn is_killer(&self, chess_move: ChessMove, ply: usize) -> bool {
+        self.killers[ply][0] == Some(chess_move) || self.killers[ply][1] == Some(chess_move)
+    }
+
+    fn store_killer(&mut self, chess_move: ChessMove, ply: usize) {
+        if self.killers[ply][0] != Some(chess_move) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(chess_move);
+        }
+    }
+
+    // pv_table[ply] = chess_move followed by pv_table[ply + 1]
+    fn update_pv(&mut self, chess_move: ChessMove, ply: usize) {
+        let mut line = MovesArray::new();
+        line.push(chess_move);
+        let mut i: usize = 0;
+        while i < self.pv_table[ply + 1].len() {
+            line.push(self.pv_table[ply + 1].data[i].unwrap());
+            i += 1;
+        }
+        self.pv_table[ply] = line;
+    }
+
+    // draws are scored from the root side's point of view, a positive contempt avoids them
+    fn draw_value(&self, ply: usize) -> Score {
+        match ply % 2 {
+            0 => Score::new(-self.config.contempt),
+            _ => Score::new(self.config.contempt),
+        }
+    }
+
+    // scores nodes where the game has ended, None if it goes on
+    // note: mates are scored by their distance from the root, so shorter mates are preferred
+    fn terminal_value(
+        &self,
+        chessboard: &ChessBoard,
+        moves_array: &MovesArray,
+        ply: usize,
+    ) -> Option<Score> {
+        if moves_array.len() == 0 {
+            return match chessboard.check_bb.data != 0 {
+                true => Some(Score::mated_in(ply)),
+                false => Some(self.draw_value(ply)),
+            };
+        }
+        // a move has to be returned at the root even if the game is drawn
+        if ply > 0 && chessboard.is_draw(ply) {
+            return Some(self.draw_value(ply));
+        }
+        None
+    }
+
+    // static evaluation from the side to move's point of view, in centipawns
+    fn evaluate(&mut self, chessboard: &ChessBoard) -> Score {
+        self.evaluator.evaluate(chessboard)
+    }
+
+    // null-move pruning: if passing the turn still fails high at reduced depth, a real move
+    // will too. returns the score to cut off with, or None if the node has to be searched.
+    // note: not done in check, at pv nodes, directly after another null move or when the
+    //       side to move only has pawns left, where zugzwang makes passing a bad guess.
+    fn null_move_search(
+        &mut self,
+        chessboard: &ChessBoard,
+        static_eval: Score,
+        beta: Score,
+        depth: usize,
+        ply: usize,
+    ) -> Option<Score> {
+        if depth < NULL_MOVE_DEPTH
+            || ply == 0
+            || self.follow_pv
+            || chessboard.check_bb.data != 0
+            || beta.is_mate()
+            || !chessboard.has_non_pawn_material(chessboard.side_to_move)
+            || static_eval < beta
+        {
+            return None;
+        }
+
+        let reduction = if depth >= 7 { 3 } else { 2 };
+        let new_depth = depth.saturating_sub(reduction + 1);
+        stat!(self.stats.null_move_tries += 1);
+        let new_chessboard = chessboard.make_null_move();
+        self.extensions[ply + 1] = self.extensions[ply];
+        self.evaluator.on_make(chessboard, None, &new_chessboard);
+        let value = -self.negamax(&new_chessboard, -beta, -beta + 1, new_depth, ply + 1, false);
+        self.evaluator.on_unmake();
+        if self.stopped || value < beta {
+            return None;
+        }
+
+        if depth >= NULL_VERIFY_DEPTH {
+            let value = self.negamax(chessboard, beta - 1, beta, new_depth, ply, false);
+            if self.stopped || value < beta {
+                return None;
+            }
+        }
+        // a mate found after passing is not a proven mate
+        stat!(self.stats.null_move_cutoffs += 1);
+        Some(beta)
+    }
+
+    // singular extension: if every move but the tt move fails low against a margin below the
+    // tt score at reduced depth, the tt move is the only good one and is searched deeper
+    fn is_singular(
+        &mut self,
+        chessboard: &ChessBoard,
+        tt_entry: Option<TTEntry>,
+        depth: usize,
+        ply: usize,
+    ) -> bool {
+        let entry = match tt_entry {
+            Some(x) => x,
+            None => return false,
+        };
+        if depth < SINGULAR_DEPTH
+            || ply == 0
+            || self.follow_pv
+            || self.excluded[ply].is_some()
+            || entry.best_move.is_none()
+            || entry.bound == Bound::Upper
+            || entry.depth + 3 < depth
+            || entry.score.is_mate()
+        {
+            return false;
+        }
+
+        let singular_beta = entry.score - SINGULAR_MARGIN * depth as i32;
+        self.excluded[ply] = entry.best_move;
+        let value =
+            self.negamax(chessboard, singular_beta - 1, singular_beta, depth / 2, ply, false);
+        self.excluded[ply] = None;
+        self.pv_table[ply].count = 0;
+        !self.stopped && value < singular_beta
+    }
+
+    // extra depth for chess_move, given that it has been made on new_chessboard
+    fn extension(
+        &self,
+        new_chessboard: &ChessBoard,
+        chess_move: ChessMove,
+        singular_move: Option<ChessMove>,
+        ply: usize,
+    ) -> usize {
+        if self.extensions[ply] >= MAX_EXTENSIONS {
+            return 0;
+        }
+        // checks
+        if new_chessboard.check_bb.data != 0 || singular_move == Some(chess_move) {
+            return 1;
+        }
+        // pawn pushes to the 7th rank, the mover is no longer the side to move
+        if self.config.pawn_extension {
+            let rank = chess_move.target() / 8;
+            match new_chessboard.mailbox[chess_move.target()] {
+                Some((Side::White, PieceType::Pawn)) if rank == 6 => return 1,
+                Some((Side::Black, PieceType::Pawn)) if rank == 1 => return 1,
+                _ => {}
+            }
+        }
+        0
+    }
+
+    fn negamax(
+        &mut self,
+        chessboard: &ChessBoard,
+        alpha: Score,
+        beta: Score,
+        depth: usize,
+        ply: usize,
+        can_null: bool,
+    ) -> Score {
+        // note: nodes visited after the search has stopped are not counted, so that a node
+        //       limited search never reports more nodes than it was given
+        if self.stopped {
+            return Score::ZERO;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        self.pv_table[ply].count = 0;
+        if self.should_stop() {
+            return Score::ZERO;
+        }
+
+        // note: the previous pv ends at every early return, otherwise the siblings of this
+        //       node would still be treated as pv nodes (no null move, no singular test)
+        // note: draws, mates and stalemates are looked for before the horizon, a position at
+        //       the last ply still has to be scored as what it is
+        let mut moves_array = chessboard.generate_moves();
+        if let Some(value) = self.terminal_value(chessboard, &moves_array, ply) {
+            self.follow_pv = false;
+            return value;
+        }
+
+        if depth == 0 || ply >= MAX_PLY {
+            self.follow_pv = false;
+            stat!(self.stats.leaf_evals += 1);
+            return self.evaluate(chessboard);
+        }
+
+        // note: pv nodes never cut off on the tt, the line below them would be lost. they are
+        //       searched with the tt move first instead, which extends the pv from it.
+        let is_pv = beta.data - alpha.data > 1;
+        let tt_entry = self.tt.probe(chessboard.current_hash, ply);
+        stat!(self.stats.tt_probes += 1);
+        if let Some(entry) = tt_entry {
+            stat!(self.stats.tt_hits += 1);
+            if !is_pv
+                && ply > 0
+                && self.excluded[ply].is_none()
+                && entry.is_cutoff(depth, alpha, beta)
+            {
+                stat!(self.stats.tt_cutoffs += 1);
+                self.follow_pv = false;
+                return entry.score;
+            }
+        }
+
+        let in_check = chessboard.check_bb.data != 0;
+        let static_eval = self.evaluate(chessboard);
+
+        if can_null && !is_pv && self.excluded[ply].is_none() {
+            if let Some(value) = self.null_move_search(chessboard, static_eval, beta, depth, ply) {
+                return value;
+            }
+        }
+        let singular_move = match self.is_singular(chessboard, tt_entry, depth, ply) {
+            true => tt_entry.and_then(|x| x.best_move),
+            false => None,
+        };
+        self.order_moves(chessboard, &mut moves_array, ply, tt_entry.and_then(|x| x.best_move));
+
+        // futility: close to the horizon, a quiet move will not make up for being this far
+        // below alpha
+        let futile = self.config.futility
+            && !is_pv
+            && !in_check
+            && depth <= FUTILITY_DEPTH
+            && !alpha.is_mate()
+            && static_eval + FUTILITY_MARGIN * depth as i32 <= alpha;
+
+        let original_alpha = alpha;
+        let mut alpha = alpha;
+        let mut value = -Score::INFINITY;
+        let mut best_move: Option<ChessMove> = None;
+        let mut moves_searched: usize = 0;
+        let mut quiets_searched: usize = 0;
+        let mut i: usize = 0;
+        while i < moves_array.len() {
+            let chess_move = match moves_array.data[i] {
+                Some(x) => x,
+                None => unreachable!(),
+            };
+            if self.excluded[ply] == Some(chess_move)
+                || (ply == 0 && self.is_root_excluded(chess_move))
+            {
+                i += 1;
+                continue;
+            }
+            let is_quiet = chessboard.is_quiet(chess_move);
+            let new_chessboard = chessboard.update_state(chess_move);
+            let gives_check = new_chessboard.check_bb.data != 0;
+            let extension = self.extension(&new_chessboard, chess_move, singular_move, ply);
+            let new_depth = depth - 1 + extension;
+            self.extensions[ply + 1] = self.extensions[ply] + extension;
+
+            // pruning quiet moves, once a move that does not get mated has been found
+            if is_quiet && !gives_check && value > -Score::MATE_BOUND {
+                let late = self.config.lmp
+                    && !is_pv
+                    && !in_check
+                    && depth <= LMP_DEPTH
+                    && quiets_searched >= LMP_BASE + depth * depth;
+                if futile || late {
+                    i += 1;
+                    continue;
+                }
+            }
+            moves_searched += 1;
+            if is_quiet {
+                quiets_searched += 1;
+            }
+            self.evaluator.on_make(chessboard, Some(chess_move), &new_chessboard);
+
+            // pvs: the first move is assumed to be best, the rest only have to be proven worse
+            // with a null window, and are searched again with the full window if they are not
+            let new_value = if moves_searched == 1 {
+                -self.negamax(&new_chessboard, -beta, -alpha, new_depth, ply + 1, true)
+            } else {
+                // lmr: late quiet moves are searched shallower first, and again at full depth
+                // if they beat alpha anyway
+                let reduction = match self.config.lmr
+                    && depth >= LMR_DEPTH
+                    && moves_searched >= LMR_MOVES
+                    && is_quiet
+                    && !gives_check
+                    && !in_check
+                    && extension == 0
+                    && !self.is_killer(chess_move, ply)
+                {
+                    true => lmr_reduction(depth, moves_searched).min(depth - 2),
+                    false => 0,
+                };
+                let mut x = -self.negamax(
+                    &new_chessboard,
+                    -alpha - 1,
+                    -alpha,
+                    new_depth - reduction,
+                    ply + 1,
+                    true,
+                );
+                stat!(self.stats.lmr_reductions += (reduction > 0) as u64);
+                if reduction > 0 && x > alpha && !self.stopped {
+                    stat!(self.stats.lmr_researches += 1);
+                    x = -self.negamax(
+                        &new_chessboard,
+                        -alpha - 1,
+                        -alpha,
+                        new_depth,
+                        ply + 1,
+                        true,
+                    );
+                }
+                match x > alpha && x < beta && !self.stopped {
+                    true => -self.negamax(&new_chessboard, -beta, -alpha, new_depth, ply + 1, true),
+                    false => x,
+                }
+            };
+            self.evaluator.on_unmake();
+            if self.stopped {
+                return Score::ZERO;
+            }
+            // value = max(value, new_value)
+            if new_value > value {
+                value = new_value;
+                best_move = Some(chess_move);
+            }
+            // alpha = max(alpha, value)
+            if value > alpha {
+                alpha = value;
+                self.update_pv(chess_move, ply);
+            }
+
+            // cutoff
+            if alpha >= beta {
+                stat!(self.stats.beta_cutoffs += 1);
+                stat!(self.stats.first_move_cutoffs += (moves_searched == 1) as u64);
+                if is_quiet {
+                    self.store_killer(chess_move, ply);
+                }
+                break;
+            }
+            i += 1;
+        }
+
+        let bound = if value >= beta {
+            Bound::Lower
+        } else if value > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // a fail low says nothing about which move is best
+        if bound == Bound::Upper {
+            best_move = None;
+        }
+        // the result of a search without some of the moves says nothing about this position
+        let root_restricted = !self.root_excluded.is_empty() || self.limits.searchmoves.len() > 0;
+        if self.excluded[ply].is_none() && (ply > 0 || !root_restricted) {
+            self.tt.store(chessboard.current_hash, best_move, value, depth, bound, ply);
+        }
+        value
+    }
+}
+
+// logarithmic in both the depth and the number of moves searched so far
+fn lmr_reduction(depth: usize, move_number: usize) -> usize {
+    (0.75 + (depth as f64).ln() * (move_number as f64).ln() / 2.25) as usize
+}
+
+fn move_index(moves_array: &MovesArray, chess_move: Option<ChessMove>) -> Option<usize> {
+    chess_move?;
+    let mut i: usize = 0;
+    while i < moves_array.len() {
+        if moves_array.data[i] == chess_move {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+impl ChessBoard {
+    // note: searches use HandCraftedEval unless given an evaluator
+    pub fn search(&self, limits: &SearchLimits) -> SearchResult {
+        let tt = TranspositionTable::new(DEFAULT_HASH_MB);
+        self.search_with_signals(
+            limits,
+            &SearchConfig::default(),
+            &tt,
+            Arc::new(SearchSignals::default()),
+        )
+    }
+
+    // search that can be stopped (or told about a ponderhit) from another thread
+    // note: best_move is None if the game is already over
+    // note: with more than one thread this is lazy smp, helper threads search the same
+    //       position and only share their work through the transposition table
+    // note: the evaluator (and its pawn table) only lives for this search, callers that
+    //       search more than once should keep their own and use search_with_evaluators
+    pub fn search_with_signals(
+        &self,
+        limits: &SearchLimits,
+        config: &SearchConfig,
+        tt: &TranspositionTable,
+        signals: Arc<SearchSignals>,
+    ) -> SearchResult {
+        let mut evaluators = vec![HandCraftedEval::default()];
+        self.search_with_evaluators(limits, config, tt, &mut evaluators, signals)
+    }
+
+    // note: evaluators[i] is the evaluator of thread i, the main thread being 0. missing
+    //       ones are cloned from evaluators[0]. like the tt, they are kept by the caller so
+    //       that their caches survive from one search to the next.
+    pub fn search_with_evaluators<E: Evaluator>(
+        &self,
+        limits: &SearchLimits,
+        config: &SearchConfig,
+        tt: &TranspositionTable,
+        evaluators: &mut Vec<E>,
+        signals: Arc<SearchSignals>,
+    ) -> SearchResult {
+        assert!(!evaluators.is_empty(), "search_with_evaluators error: no evaluator!");
+        while evaluators.len() < config.threads {
+            evaluators.push(evaluators[0].clone());
+        }
+        let (main_evaluator, helper_evaluators) = match evaluators.split_first_mut() {
+            Some(x) => x,
+            None => unreachable!(),
+        };
+        signals.nodes.store(0, Ordering::SeqCst);
+        // helpers only stop on signals.stop, or after the same depth as the main thread
+        // note: with more than one thread a node limit is only approximate
+        let helper_limits = SearchLimits {
+            depth: Some(limits.max_depth()),
+            searchmoves: limits.searchmoves,
+            ..SearchLimits::default()
+        };
+        // helpers only look for the best move, extra lines are left to the main thread
+        let helper_config = SearchConfig { multipv: 1, ..*config };
+        let chessboard: &ChessBoard = self;
+
+        let (mut result, main) = thread::scope(|scope| {
+            let mut helpers = Vec::new();
+            let mut helper_evaluators = helper_evaluators.iter_mut();
+            let mut thread_id: usize = 1;
+            while thread_id < config.threads {
+                let signals = Arc::clone(&signals);
+                let evaluator = match helper_evaluators.next() {
+                    Some(x) => x,
+                    None => unreachable!(),
+                };
+                let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn_scoped(
+                    scope,
+                    move || {
+                        let side = chessboard.side_to_move;
+                        let mut helper = Searcher::new(
+                            helper_limits,
+                            helper_config,
+                            tt,
+                            evaluator,
+                            side,
+                            signals,
+                            thread_id,
+                        );
+                        helper.iterative_deepening(chessboard)
+                    },
+                );
+                match handle {
+                    Ok(x) => helpers.push(x),
+                    Err(_) => break,
+                }
+                thread_id += 1;
+            }
+
+            let side = chessboard.side_to_move;
+            let mut main =
+                Searcher::new(*limits, *config, tt, main_evaluator, side, Arc::clone(&signals), 0);
+            let mut result = main.iterative_deepening(chessboard);
+
+            // uci: while pondering or in infinite mode bestmove may only be sent after stop/ponderhit
+            while !signals.is_stopped() && (limits.infinite || signals.is_pondering()) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            signals.stop.store(true, Ordering::SeqCst);
+
+            // the deepest completed iteration wins, ties go to the better score
+            let mut stats = result.stats.clone();
+            let mut from_helper = false;
+            for handle in helpers {
+                let helper_result = match handle.join() {
+                    Ok(x) => x,
+                    Err(_) => panic!("search_with_evaluators error: helper thread panicked!"),
+                };
+                stats += &helper_result.stats;
+                if config.multipv == 1
+                    && helper_result.best_move.is_some()
+                    && (helper_result.depth, helper_result.score) > (result.depth, result.score)
+                {
+                    result = helper_result;
+                    from_helper = true;
+                }
+            }
+            if from_helper {
+                main.print_info(&result);
+            }
+            result.stats = stats;
+            (result, main)
+        });
+        result.nodes = main.total_nodes();
+        if config.debug {
+            println!("{}", result.stats);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // searches fen to depth with contempt, on a thread with enough stack for the search
+    fn search_fen(fen: &str, depth: usize, contempt: i32) -> (ChessBoard, SearchResult) {
+        search_moves(fen, &[], depth, contempt)
+    }
+
+    // same as search_fen, from the position after moves have been played from fen
+    fn search_moves(
+        fen: &str,
+        moves: &[&str],
+        depth: usize,
+        contempt: i32,
+    ) -> (ChessBoard, SearchResult) {
+        let mut chessboard = ChessBoard::from_fen(fen);
+        for move_str in moves {
+            chessboard = match chessboard.find_uci_move(move_str) {
+                Some(x) => chessboard.update_state(x),
+                None => panic!("search_moves error: illegal move {}!", move_str),
+            };
+        }
+        let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn(move || {
+            let limits = SearchLimits { depth: Some(depth), ..SearchLimits::default() };
+            let config = SearchConfig { contempt, quiet: true, ..SearchConfig::default() };
+            let tt = TranspositionTable::new(1);
+            let signals = Arc::new(SearchSignals::default());
+            let result = chessboard.search_with_signals(&limits, &config, &tt, signals);
+            (chessboard, result)
+        });
+        match handle.map(|x| x.join()) {
+            Ok(Ok(x)) => x,
+            _ => panic!("search_fen error: search thread failed!"),
+        }
+    }
+
+    // searches fen with the limits of a uci "go" command
+    fn search_go(fen: &str, go: &str) -> SearchResult {
+        let (fen, go) = (fen.to_string(), go.to_string());
+        let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn(move || {
+            let chessboard = ChessBoard::from_fen(&fen);
+            let limits = SearchLimits::from_uci(&go, &chessboard);
+            let config = SearchConfig { quiet: true, ..SearchConfig::default() };
+            let tt = TranspositionTable::new(1);
+            let signals = Arc::new(SearchSignals::default());
+            chessboard.search_with_signals(&limits, &config, &tt, signals)
+        });
+        match handle.map(|x| x.join()) {
+            Ok(Ok(x)) => x,
+            _ => panic!("search_go error: search thread failed!"),
+        }
+    }
+
+    #[test]
+    fn avoids_stalemating_the_opponent() {
+        // any king move stalemates the black king on a8
+        let (chessboard, result) = search_fen("k7/8/1Q6/8/8/8/8/7K w - - 0 1", 6, 0);
+        let best_move = match result.best_move {
+            Some(x) => x,
+            None => panic!("avoids_stalemating_the_opponent error: no move found!"),
+        };
+        let new_chessboard = chessboard.update_state(best_move);
+        assert!(new_chessboard.generate_moves().len() > 0, "{} stalemates the opponent", best_move);
+        // still a queen up
+        assert!(result.score > Score::new(900), "{}", result.score);
+    }
+
+    #[test]
+    fn losing_side_finds_the_stalemate() {
+        // black is lost unless the queen gets itself taken with stalemate, or keeps checking
+        let (_, result) = search_fen("7k/5Q2/6K1/8/8/8/8/q7 b - - 0 1", 6, 20);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.score, Score::new(-20));
+    }
+
+    #[test]
+    fn mates_are_scored_by_distance() {
+        let (_, result) = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4, 0);
+        assert_eq!(result.score, Score::mate_in(1));
+        assert_eq!(result.score.mate_moves(), Some(1));
+        assert_eq!(result.best_move.map(|x| x.to_string()), Some("a1a8".to_string()));
+
+        let (_, result) = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1", 4, 0);
+        assert_ne!(result.score.mate_moves(), Some(-1));
+
+        // the king's only move runs into a rook mate on the back rank
+        let (_, result) = search_fen("7k/8/8/8/8/1r6/r7/7K w - - 0 1", 4, 0);
+        assert_eq!(result.score.mate_moves(), Some(-1));
+        assert_eq!(result.score, Score::mated_in(2));
+    }
+
+    #[test]
+    fn terminal_positions_at_the_horizon() {
+        // black can only save itself by repeating the position a third time, at the last ply
+        let moves = ["d1e1", "h8g6", "e1d1", "g6h8", "d1e1", "h8g6", "e1d1"];
+        let (_, result) = search_moves("k6n/8/8/8/8/8/3R4/3Q3K w - - 0 1", &moves, 1, 0);
+        assert_eq!(result.best_move.map(|x| x.to_string()), Some("g6h8".to_string()));
+        assert_eq!(result.score, Score::ZERO);
+
+        // the stalemate is only on the board at the last ply, and is not worth a queen
+        let (chessboard, result) = search_fen("k7/8/1Q6/8/8/8/8/7K w - - 0 1", 1, 0);
+        let best_move = match result.best_move {
+            Some(x) => x,
+            None => panic!("terminal_positions_at_the_horizon error: no move found!"),
+        };
+        let new_chessboard = chessboard.update_state(best_move);
+        assert!(new_chessboard.generate_moves().len() > 0, "{} stalemates the opponent", best_move);
+    }
+
+    #[test]
+    fn pv_reaches_past_the_first_move() {
+        // the second search finds every pv node of the first in the tt
+        let handle = thread::Builder::new().stack_size(SEARCH_STACK_SIZE).spawn(|| {
+            let chessboard = ChessBoard::default();
+            let limits = SearchLimits { depth: Some(6), ..SearchLimits::default() };
+            let config = SearchConfig { quiet: true, ..SearchConfig::default() };
+            let tt = TranspositionTable::new(1);
+            let mut results = Vec::new();
+            while results.len() < 2 {
+                let signals = Arc::new(SearchSignals::default());
+                results.push(chessboard.search_with_signals(&limits, &config, &tt, signals));
+            }
+            (chessboard, results)
+        });
+        let (chessboard, results) = match handle.map(|x| x.join()) {
+            Ok(Ok(x)) => x,
+            _ => panic!("pv_reaches_past_the_first_move error: search thread failed!"),
+        };
+        for result in results.iter() {
+            assert!(result.pv.len() > 1, "pv {}", pv_string(&result.pv));
+            assert_eq!(result.best_move, result.pv.first().copied());
+            assert!(result.ponder_move.is_some());
+            assert_eq!(result.ponder_move, result.pv.get(1).copied());
+
+            // every move of the pv is legal where it is played
+            let mut chessboard = chessboard.clone();
+            for chess_move in result.pv.iter() {
+                assert!(move_index(&chessboard.generate_moves(), Some(*chess_move)).is_some());
+                chessboard = chessboard.update_state(*chess_move);
+            }
+        }
+    }
+
+    #[test]
+    fn stalemated_root_is_a_draw() {
+        let (_, result) = search_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1", 4, 20);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::new(-20));
+        assert_eq!(result.to_uci(), "bestmove 0000");
+    }
+
+    #[test]
+    fn fixed_nodes_search_is_reproducible() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let first = search_go(fen, "nodes 20000");
+        let second = search_go(fen, "nodes 20000");
+        assert!(first.best_move.is_some());
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.nodes, 20000);
+    }
+
+    #[test]
+    fn searchmoves_restricts_the_root_moves() {
+        let listed = ["g2g4", "a2a3", "h2h3"];
+        let result = search_go(INITIAL_CHESS_POS_FEN, "depth 5 searchmoves g2g4 a2a3 h2h3");
+        let best_move = result.best_move.map(|x| x.to_string()).unwrap_or_default();
+        assert!(listed.contains(&best_move.as_str()), "{} was not listed", best_move);
+
+        // only the listed move is played, even with a mate in one on the board
+        let result = search_go("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "depth 4 searchmoves g1f1");
+        assert_eq!(result.best_move.map(|x| x.to_string()), Some("g1f1".to_string()));
+        assert!(result.score < Score::MATE_BOUND);
+
+        // the moves end at the first one that is not legal, the rest is parsed as usual
+        let chessboard = ChessBoard::default();
+        let limits = SearchLimits::from_uci("searchmoves e2e4 e7e5 d2d4 nodes 100", &chessboard);
+        assert_eq!(limits.searchmoves.len(), 1);
+        assert_eq!(limits.nodes, Some(100));
+    }
+}
@@ -0,0 +1,118 @@
+#![allow(dead_code)]
+
+use std::fmt::Display;
+use std::ops::AddAssign;
+
+/* counters for tuning the search.
+
+note: the counters are only updated in builds with the "stats" feature, which is off by
+      default: build with --features stats to collect them. without it they stay zero and
+      cost nothing. */
+
+// runs the statement only in builds with the "stats" feature
+macro_rules! stat {
+    ($($x:tt)*) => {
+        #[cfg(feature = "stats")]
+        {
+            $($x)*;
+        }
+    };
+}
+pub(crate) use stat;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchStats {
+    pub nodes: u64,
+    // static evals returned at the horizon (depth 0 or MAX_PLY), there is no quiescence search
+    pub leaf_evals: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub tt_cutoffs: u64,
+    pub beta_cutoffs: u64,
+    // beta cutoffs caused by the first move searched, a measure of move ordering
+    pub first_move_cutoffs: u64,
+    pub null_move_tries: u64,
+    pub null_move_cutoffs: u64,
+    pub lmr_reductions: u64,
+    pub lmr_researches: u64,
+    // nodes searched so far at the end of each completed iteration of the main thread
+    pub iteration_nodes: Vec<u64>,
+}
+
+pub const STATS_ENABLED: bool = cfg!(feature = "stats");
+
+impl SearchStats {
+    // nodes of each iteration divided by those of the one before
+    pub fn branching_factors(&self) -> Vec<f64> {
+        let mut nodes = vec![0];
+        nodes.extend_from_slice(&self.iteration_nodes);
+        let nodes = nodes.windows(2).map(|x| x[1] - x[0]).collect::<Vec<u64>>();
+        nodes.windows(2).map(|x| x[1] as f64 / x[0].max(1) as f64).collect()
+    }
+}
+
+// in percent, 0 if there was nothing to count
+fn rate(count: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => count as f64 * 100.0 / total as f64,
+    }
+}
+
+// note: the iterations are those of the thread added to
+impl AddAssign<&SearchStats> for SearchStats {
+    fn add_assign(&mut self, rhs: &SearchStats) {
+        self.nodes += rhs.nodes;
+        self.leaf_evals += rhs.leaf_evals;
+        self.tt_probes += rhs.tt_probes;
+        self.tt_hits += rhs.tt_hits;
+        self.tt_cutoffs += rhs.tt_cutoffs;
+        self.beta_cutoffs += rhs.beta_cutoffs;
+        self.first_move_cutoffs += rhs.first_move_cutoffs;
+        self.null_move_tries += rhs.null_move_tries;
+        self.null_move_cutoffs += rhs.null_move_cutoffs;
+        self.lmr_reductions += rhs.lmr_reductions;
+        self.lmr_researches += rhs.lmr_researches;
+    }
+}
+
+// uci notation, one "info string" line per group of counters
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !STATS_ENABLED {
+            return write!(f, "info string search statistics are not compiled in");
+        }
+        writeln!(f, "info string nodes {} leaf evals {}", self.nodes, self.leaf_evals)?;
+        writeln!(
+            f,
+            "info string tt probes {} hits {} ({:.1}%) cutoffs {}",
+            self.tt_probes,
+            self.tt_hits,
+            rate(self.tt_hits, self.tt_probes),
+            self.tt_cutoffs
+        )?;
+        writeln!(
+            f,
+            "info string beta cutoffs {} first move {:.1}%",
+            self.beta_cutoffs,
+            rate(self.first_move_cutoffs, self.beta_cutoffs)
+        )?;
+        writeln!(
+            f,
+            "info string null move tries {} cutoffs {} ({:.1}%)",
+            self.null_move_tries,
+            self.null_move_cutoffs,
+            rate(self.null_move_cutoffs, self.null_move_tries)
+        )?;
+        writeln!(
+            f,
+            "info string lmr reductions {} researches {} ({:.1}%)",
+            self.lmr_reductions,
+            self.lmr_researches,
+            rate(self.lmr_researches, self.lmr_reductions)
+        )?;
+        let factors = self.branching_factors();
+        let factors = factors.iter().map(|x| format!("{:.2}", x)).collect::<Vec<String>>();
+        write!(f, "info string branching factors {}", factors.join(" "))
+    }
+}
@@ -0,0 +1,164 @@
+#![allow(dead_code)]
+
+use std::fmt::Display;
+
+use crate::bitboard::*;
+use crate::chessboard::*;
+use crate::endgame::*;
+use crate::eval::*;
+use crate::king::*;
+use crate::pawns::*;
+use crate::pieces::*;
+
+/* breakdown of the hand crafted evaluation, for finding out why a position is misjudged.
+
+note: the terms of each side are from that side's point of view, the total is white's
+      minus black's. the final score is the same as HandCraftedEval gives. */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EvalTerm {
+    pub name: &'static str,
+    pub white: TaperedScore,
+    pub black: TaperedScore,
+}
+
+impl EvalTerm {
+    pub fn total(&self) -> TaperedScore {
+        self.white - self.black
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalTrace {
+    pub terms: Vec<EvalTerm>,
+    pub phase: i32,
+    // in centipawns, from white's point of view
+    pub score: i32,
+    // name of the endgame that replaced or scaled the score, see src/endgame.rs
+    pub endgame: Option<&'static str>,
+    pub side_to_move: Side,
+}
+
+// material and piece-square values of side's pieces, from side's point of view
+fn material_and_pst(
+    chessboard: &ChessBoard,
+    side: Side,
+    params: &EvalParams,
+) -> (TaperedScore, TaperedScore) {
+    let o = 6 * side as usize;
+    let mut material = TaperedScore::ZERO;
+    let mut pst = TaperedScore::ZERO;
+    let mut i: usize = 0;
+    while i < 6 {
+        let mut pieces = chessboard.piece_bbs[o + i];
+        while let Some(square) = pieces.lsb_index() {
+            pieces = pieces.pop_bit(square);
+            // black's pieces use the square mirrored to white's side
+            let square = if o == 0 { square } else { square ^ 56 };
+            material += params.material[i];
+            pst += params.piece_square(i, square) - params.material[i];
+        }
+        i += 1;
+    }
+    (material, pst)
+}
+
+impl ChessBoard {
+    pub fn eval_trace(&self) -> EvalTrace {
+        self.eval_trace_with(&DEFAULT_PARAMS)
+    }
+
+    pub fn eval_trace_with(&self, params: &EvalParams) -> EvalTrace {
+        let (white_material, white_pst) = material_and_pst(self, Side::White, params);
+        let (black_material, black_pst) = material_and_pst(self, Side::Black, params);
+        let (white_pawns, white_passed) =
+            pawn_terms(Side::White, self.piece_bbs[5], self.piece_bbs[11], params);
+        let (black_pawns, black_passed) =
+            pawn_terms(Side::Black, self.piece_bbs[11], self.piece_bbs[5], params);
+
+        let mut terms = Vec::new();
+        let mut push = |name: &'static str, white: TaperedScore, black: TaperedScore| {
+            terms.push(EvalTerm { name, white, black });
+        };
+        push("Material", white_material, black_material);
+        push("PSQT", white_pst, black_pst);
+        push("Pawns", white_pawns, black_pawns);
+        push(
+            "Passed",
+            passed_terms(self, Side::White, white_passed, params),
+            passed_terms(self, Side::Black, black_passed, params),
+        );
+        push("Mobility", mobility(self, Side::White, params), mobility(self, Side::Black, params));
+        push(
+            "Pieces",
+            piece_activity(self, Side::White, params),
+            piece_activity(self, Side::Black, params),
+        );
+        push(
+            "King attack",
+            king_attack(self, Side::White, params),
+            king_attack(self, Side::Black, params),
+        );
+        push(
+            "King shelter",
+            king_shelter(self, Side::White, params),
+            king_shelter(self, Side::Black, params),
+        );
+
+        let total = terms.iter().fold(TaperedScore::ZERO, |acc, x| acc + x.total());
+        EvalTrace {
+            terms,
+            phase: self.phase,
+            score: endgame_value(self, total),
+            endgame: probe_endgame(self).map(|x| x.0.name),
+            side_to_move: self.side_to_move,
+        }
+    }
+}
+
+impl EvalTrace {
+    pub fn total(&self) -> TaperedScore {
+        self.terms.iter().fold(TaperedScore::ZERO, |acc, x| acc + x.total())
+    }
+}
+
+// centipawns as pawns, "+1.23"
+fn pawns(x: i32) -> String {
+    format!("{:+.2}", x as f64 / 100.0)
+}
+
+fn columns(x: TaperedScore) -> String {
+    format!("{:>7} {:>7}", pawns(x.mg), pawns(x.eg))
+}
+
+// a table of the terms in pawns, one line per term
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = "-------------+-----------------+-----------------+----------------";
+        writeln!(f, "         Term |      White      |      Black      |      Total")?;
+        writeln!(f, "              |    MG      EG   |    MG      EG   |    MG      EG")?;
+        writeln!(f, " {}", line)?;
+        for term in self.terms.iter() {
+            writeln!(
+                f,
+                " {:>12} | {} | {} | {}",
+                term.name,
+                columns(term.white),
+                columns(term.black),
+                columns(term.total())
+            )?;
+        }
+        writeln!(f, " {}", line)?;
+        writeln!(f, " {:>12} | {:>15} | {:>15} | {}", "Total", "", "", columns(self.total()))?;
+        writeln!(f)?;
+        writeln!(f, "Phase: {} / {} (middlegame)", self.phase.min(MAX_PHASE), MAX_PHASE)?;
+        if let Some(name) = self.endgame {
+            writeln!(f, "Endgame: {}", name)?;
+        }
+        let side = match self.side_to_move {
+            Side::White => "white",
+            Side::Black => "black",
+        };
+        write!(f, "Final evaluation: {} (white side), {} to move", pawns(self.score), side)
+    }
+}
@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::chessmove::*;
+use crate::score::*;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 1024;
+
+// what the stored score says about the true score of the position
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower, // failed high, true score >= score
+    Upper, // failed low, true score <= score
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TTEntry {
+    pub key: u64,
+    pub best_move: Option<ChessMove>,
+    pub score: Score,
+    pub depth: usize,
+    pub bound: Bound,
+}
+
+/*  entries are packed into a single u64:
+bits  0-15  best move, 0 if none (0 would be h1h1, which is never a legal move)
+bits 16-31  score, as an i16
+bits 32-39  depth
+bits 40-41  bound, 0 if the slot is empty */
+
+impl TTEntry {
+    // true if the entry can replace searching the node
+    pub fn is_cutoff(&self, depth: usize, alpha: Score, beta: Score) -> bool {
+        self.depth >= depth
+            && match self.bound {
+                Bound::Exact => true,
+                Bound::Lower => self.score >= beta,
+                Bound::Upper => self.score <= alpha,
+            }
+    }
+
+    const fn pack(&self) -> u64 {
+        let best_move = match self.best_move {
+            Some(x) => x.data as u64,
+            None => 0,
+        };
+        let score = (self.score.data as i16 as u16) as u64;
+        let depth = if self.depth < 255 { self.depth as u64 } else { 255 };
+        let bound: u64 = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        best_move | score << 16 | depth << 32 | bound << 40
+    }
+
+    const fn unpack(key: u64, data: u64) -> Option<TTEntry> {
+        let bound = match (data >> 40) & 0b11 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        let best_move = match data & 0xffff {
+            0 => None,
+            x => Some(ChessMove { data: x as u16 }),
+        };
+        let score = Score::new(((data >> 16) & 0xffff) as u16 as i16 as i32);
+        let depth = ((data >> 32) & 0xff) as usize;
+        Some(TTEntry { key, best_move, score, depth, bound })
+    }
+}
+
+// note: the key is stored xor-ed with the data. threads read and write slots without
+//       locking, a slot torn by two writers fails the key check instead of being trusted.
+struct TTSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<TTSlot>,
+}
+
+pub type TT = TranspositionTable;
+
+impl TranspositionTable {
+    // note: the number of entries is rounded down to a power of two
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let size_mb = size_mb.clamp(1, MAX_HASH_MB);
+        let count = (size_mb << 20) / std::mem::size_of::<TTSlot>();
+        let count = 1usize << (usize::BITS - 1 - count.leading_zeros());
+        let slots = (0..count)
+            .map(|_| TTSlot { key: AtomicU64::new(0), data: AtomicU64::new(0) })
+            .collect();
+        TranspositionTable { slots }
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key as usize) & (self.slots.len() - 1)
+    }
+
+    fn load(&self, index: usize) -> Option<TTEntry> {
+        let data = self.slots[index].data.load(Ordering::Relaxed);
+        let key = self.slots[index].key.load(Ordering::Relaxed) ^ data;
+        TTEntry::unpack(key, data)
+    }
+
+    // note: mate scores are returned relative to the root again
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TTEntry> {
+        match self.load(self.index(key)) {
+            Some(entry) if entry.key == key => {
+                Some(TTEntry { score: entry.score.adjust_from_tt(ply), ..entry })
+            }
+            _ => None,
+        }
+    }
+
+    // prefers deeper and exact entries, entries of other positions are always replaced
+    pub fn store(
+        &self,
+        key: u64,
+        best_move: Option<ChessMove>,
+        score: Score,
+        depth: usize,
+        bound: Bound,
+        ply: usize,
+    ) {
+        let index = self.index(key);
+        let old = self.load(index);
+        if let Some(old) = old {
+            if old.key == key && old.depth > depth && bound != Bound::Exact {
+                return;
+            }
+        }
+        // keep the old move if the new search did not find one
+        let best_move = match (best_move, old) {
+            (None, Some(old)) if old.key == key => old.best_move,
+            _ => best_move,
+        };
+        let entry = TTEntry { key, best_move, score: score.adjust_to_tt(ply), depth, bound };
+        let data = entry.pack();
+        self.slots[index].key.store(key ^ data, Ordering::Relaxed);
+        self.slots[index].data.store(data, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,345 @@
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Instant;
+
+use crate::chessboard::*;
+use crate::endgame::*;
+use crate::eval::*;
+
+/* texel tuning of EvalParams against the results of games.
+
+the positions file has one quiet position per line, a fen followed by the result of the
+game from white's point of view: 1-0, 0-1, 1/2-1/2, or 1.0, 0.5, 0.0. the result may be
+quoted or in brackets and the move counters of the fen may be left out, so the usual epd
+formats ('<fen> c9 "1-0";', '<fen> [0.5]') load as they are. lines that do not parse are
+skipped.
+
+the loss is the mean squared error between the result and sigmoid(K * eval / 400), with
+the eval in centipawns from white's point of view. K is fitted first to the untuned params,
+then the params are fitted by gradient descent (adam) with K fixed.
+
+the evaluation is linear in the params, so the gradient of every position is computed once
+up front: the eval of a position is the sum of coefficient * weight over the weights, tapered
+by the phase. the coefficients of material and the piece-square tables are the piece counts,
+those of the other weights are measured through eval_trace_with. the known endgames of
+src/endgame.rs replace or scale the eval, which is not linear in the params, so positions
+with one are left out.
+
+note: the tuned params have to be pasted over DEFAULT_PARAMS to be used, the incremental
+      piece-square eval of update_state is built from DEFAULT_PARAMS at compile time. */
+
+pub const TUNE_ITERATIONS: usize = 1000;
+pub const TUNE_OUTPUT: &str = "tuned_params.rs";
+// adam step size, in centipawns
+const LEARNING_RATE: f64 = 1.0;
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+// weights are set to this when measuring their coefficients, so that the integer divisions
+// of the eval do not round the coefficients away
+const COEFFICIENT_UNIT: i32 = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningEntry {
+    // 1.0 for a white win, 0.5 for a draw, 0.0 for a black win
+    pub result: f64,
+    // middlegame share of the eval, phase / MAX_PHASE
+    pub mg_share: f64,
+    // the eval with all weights 0, from white's point of view
+    pub base: TaperedScore,
+    // (weight index, mg coefficient, eg coefficient), only the weights the position uses
+    pub coefficients: Vec<(usize, f64, f64)>,
+}
+
+// parses '<fen> <result>', see the top of the file for the accepted forms
+pub fn parse_tuning_line(line: &str) -> Option<(String, f64)> {
+    let tokens: Vec<&str> = line
+        .split_ascii_whitespace()
+        .map(|x| x.trim_matches(|c| c == '"' || c == ';' || c == '[' || c == ']'))
+        .filter(|x| !x.is_empty())
+        .collect();
+    if tokens.len() < 5 || tokens[0].starts_with('#') {
+        return None;
+    }
+    // board, side, castling and en passant, then the move counters if they are there
+    let mut fen = tokens[..4].to_vec();
+    let mut rest = &tokens[4..];
+    match (rest.first().map(|x| x.parse::<u32>()), rest.get(1).map(|x| x.parse::<u32>())) {
+        (Some(Ok(_)), Some(Ok(_))) => {
+            fen.extend_from_slice(&rest[..2]);
+            rest = &rest[2..];
+        }
+        _ => fen.extend_from_slice(&["0", "1"]),
+    }
+    // the last one, so that a score in front of it (as in datagen output) is not taken for it
+    let result = rest.iter().rev().find_map(|x| match *x {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => match x.parse::<f64>() {
+            Ok(y) if y == 0.0 || y == 0.5 || y == 1.0 => Some(y),
+            _ => None,
+        },
+    })?;
+    Some((fen.join(" "), result))
+}
+
+// material and piece-square weight indices of a white piece on square
+// note: a black piece uses the indices of the square mirrored to white's side, negated
+const fn piece_square_indices(piece: usize, square: usize) -> (usize, usize) {
+    (piece, 6 + 64 * piece + 63 - square)
+}
+
+// coefficients of the material and piece-square weights, white's pieces minus black's
+fn piece_square_coefficients(chessboard: &ChessBoard) -> Vec<(usize, f64, f64)> {
+    let mut counts = [0i32; PIECE_SQUARE_PARAMS];
+    let mut piece: usize = 0;
+    while piece < 12 {
+        let (sign, mirror) = if piece < 6 { (1, 0) } else { (-1, 56) };
+        let mut pieces = chessboard.piece_bbs[piece];
+        while let Some(square) = pieces.lsb_index() {
+            pieces = pieces.pop_bit(square);
+            let (material, pst) = piece_square_indices(piece % 6, square ^ mirror);
+            counts[material] += sign;
+            counts[pst] += sign;
+        }
+        piece += 1;
+    }
+    let counts = counts.iter().enumerate().filter(|x| *x.1 != 0);
+    counts.map(|(i, &x)| (i, x as f64, x as f64)).collect()
+}
+
+pub fn tuning_entry(chessboard: &ChessBoard, result: f64) -> TuningEntry {
+    let zero = [0; 2 * PARAM_COUNT];
+    let base = chessboard.eval_trace_with(&EvalParams::from_vec(&zero)).total();
+    let mut coefficients = piece_square_coefficients(chessboard);
+    let mut i: usize = PIECE_SQUARE_PARAMS;
+    while i < PARAM_COUNT {
+        let mut values = zero;
+        values[2 * i] = COEFFICIENT_UNIT;
+        values[2 * i + 1] = COEFFICIENT_UNIT;
+        let x = chessboard.eval_trace_with(&EvalParams::from_vec(&values)).total() - base;
+        if x != TaperedScore::ZERO {
+            let unit = COEFFICIENT_UNIT as f64;
+            coefficients.push((i, x.mg as f64 / unit, x.eg as f64 / unit));
+        }
+        i += 1;
+    }
+    TuningEntry {
+        result,
+        mg_share: chessboard.phase.min(MAX_PHASE) as f64 / MAX_PHASE as f64,
+        base,
+        coefficients,
+    }
+}
+
+pub fn load_tuning_entries(path: &str) -> io::Result<Vec<TuningEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    let mut skipped: usize = 0;
+    let mut endgames: usize = 0;
+    for line in reader.lines() {
+        let (fen, result) = match parse_tuning_line(&line?) {
+            Some(x) => x,
+            None => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let chessboard = ChessBoard::from_fen(&fen);
+        match probe_endgame(&chessboard) {
+            Some(_) => endgames += 1,
+            None => entries.push(tuning_entry(&chessboard, result)),
+        }
+    }
+    println!(
+        "loaded {} positions, skipped {} lines and {} known endgames",
+        entries.len(),
+        skipped,
+        endgames
+    );
+    Ok(entries)
+}
+
+// the eval in centipawns, from white's point of view, of weights as in EvalParams::to_vec
+pub fn linear_eval(entry: &TuningEntry, weights: &[f64]) -> f64 {
+    let mut mg = entry.base.mg as f64;
+    let mut eg = entry.base.eg as f64;
+    for &(i, mg_coefficient, eg_coefficient) in entry.coefficients.iter() {
+        mg += mg_coefficient * weights[2 * i];
+        eg += eg_coefficient * weights[2 * i + 1];
+    }
+    mg * entry.mg_share + eg * (1.0 - entry.mg_share)
+}
+
+// expected result from the eval
+pub fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+pub fn tuning_loss(entries: &[TuningEntry], weights: &[f64], k: f64) -> f64 {
+    let total: f64 = entries
+        .iter()
+        .map(|x| {
+            let error = x.result - sigmoid(k, linear_eval(x, weights));
+            error * error
+        })
+        .sum();
+    total / entries.len() as f64
+}
+
+// golden section search of the K that fits the weights best
+pub fn optimise_k(entries: &[TuningEntry], weights: &[f64]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (0.0, 10.0);
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let mut loss_c = tuning_loss(entries, weights, c);
+    let mut loss_d = tuning_loss(entries, weights, d);
+    while b - a > 1e-4 {
+        if loss_c < loss_d {
+            b = d;
+            d = c;
+            loss_d = loss_c;
+            c = b - ratio * (b - a);
+            loss_c = tuning_loss(entries, weights, c);
+        } else {
+            a = c;
+            c = d;
+            loss_c = loss_d;
+            d = a + ratio * (b - a);
+            loss_d = tuning_loss(entries, weights, d);
+        }
+    }
+    (a + b) / 2.0
+}
+
+pub fn tuning_gradient(entries: &[TuningEntry], weights: &[f64], k: f64) -> Vec<f64> {
+    let mut gradient = vec![0.0; weights.len()];
+    let scale = 2.0 * k * 10f64.ln() / 400.0 / entries.len() as f64;
+    for entry in entries.iter() {
+        let s = sigmoid(k, linear_eval(entry, weights));
+        let x = (s - entry.result) * s * (1.0 - s) * scale;
+        for &(i, mg_coefficient, eg_coefficient) in entry.coefficients.iter() {
+            gradient[2 * i] += x * mg_coefficient * entry.mg_share;
+            gradient[2 * i + 1] += x * eg_coefficient * (1.0 - entry.mg_share);
+        }
+    }
+    gradient
+}
+
+// fits params to the entries, returns the tuned params and K
+pub fn tune_params(
+    entries: &[TuningEntry],
+    params: &EvalParams,
+    iterations: usize,
+) -> (EvalParams, f64) {
+    assert!(!entries.is_empty(), "tune_params error: no positions to tune on!");
+    let mut weights: Vec<f64> = params.to_vec().into_iter().map(|x| x as f64).collect();
+    let k = optimise_k(entries, &weights);
+    println!("K = {:.4}, loss {:.6}", k, tuning_loss(entries, &weights, k));
+
+    let mut m = vec![0.0; weights.len()];
+    let mut v = vec![0.0; weights.len()];
+    let mut t: usize = 1;
+    while t <= iterations {
+        let gradient = tuning_gradient(entries, &weights, k);
+        let mut i: usize = 0;
+        while i < weights.len() {
+            m[i] = BETA1 * m[i] + (1.0 - BETA1) * gradient[i];
+            v[i] = BETA2 * v[i] + (1.0 - BETA2) * gradient[i] * gradient[i];
+            let m_hat = m[i] / (1.0 - BETA1.powi(t as i32));
+            let v_hat = v[i] / (1.0 - BETA2.powi(t as i32));
+            weights[i] -= LEARNING_RATE * m_hat / (v_hat.sqrt() + EPSILON);
+            i += 1;
+        }
+        if t.is_multiple_of(100) || t == iterations {
+            println!("iteration {}, loss {:.6}", t, tuning_loss(entries, &weights, k));
+        }
+        t += 1;
+    }
+    let values: Vec<i32> = weights.iter().map(|x| x.round() as i32).collect();
+    (EvalParams::from_vec(&values), k)
+}
+
+// tunes DEFAULT_PARAMS on the positions file and writes the result to output as rust source
+pub fn tune(path: &str, iterations: usize, output: &str) -> io::Result<()> {
+    let start = Instant::now();
+    let entries = load_tuning_entries(path)?;
+    if entries.is_empty() {
+        println!("no positions to tune on");
+        return Ok(());
+    }
+    let (params, k) = tune_params(&entries, &DEFAULT_PARAMS, iterations);
+    let weights: Vec<f64> = params.to_vec().into_iter().map(|x| x as f64).collect();
+    let loss = tuning_loss(&entries, &weights, k);
+
+    let mut file = File::create(output)?;
+    writeln!(
+        file,
+        "// tuned on {} positions of {}, K = {:.4}, loss {:.6}",
+        entries.len(),
+        path,
+        k,
+        loss
+    )?;
+    write!(file, "{}", params.to_rust("DEFAULT_PARAMS"))?;
+    println!("wrote {} in {} ms", output, start.elapsed().as_millis());
+    println!("paste it over DEFAULT_PARAMS in src/eval.rs to use the tuned params");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEN: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+
+    #[test]
+    fn parses_results_in_every_form() {
+        let lines = [
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 1-0", 1.0),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 c9 \"0-1\";", 0.0),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 [0.5]", 0.5),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 1/2-1/2", 0.5),
+            // datagen output, the score in front of the result is not a result
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 | 1 | 1.0", 1.0),
+        ];
+        for (line, result) in lines {
+            assert_eq!(parse_tuning_line(line), Some((FEN.to_string(), result)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn skips_lines_without_a_result() {
+        let lines = [
+            "",
+            "# rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 1-0",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 0.7",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq",
+        ];
+        for line in lines {
+            assert_eq!(parse_tuning_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn known_endgames_are_not_loaded() {
+        let path = std::env::temp_dir().join("stowockfish_tune_test.epd");
+        let path = path.to_str().unwrap().to_string();
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "{} 1/2-1/2", FEN).unwrap();
+        writeln!(file, "8/8/8/4k3/8/8/8/KQ6 w - - 0 1 1-0").unwrap();
+        writeln!(file, "8/8/8/4k3/8/8/8/KNN5 w - - 0 1 1/2-1/2").unwrap();
+        writeln!(file, "not a position").unwrap();
+        drop(file);
+
+        let entries = load_tuning_entries(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].result, 0.5);
+    }
+}